@@ -0,0 +1,95 @@
+//! Audio output with cpal
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+//!
+//! The emulator samples go into a bounded queue that the output stream
+//! drains from its own thread. When the emulator runs ahead the oldest
+//! samples are dropped so latency stays bounded; when it falls behind the
+//! stream plays silence until new samples arrive.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+// Most sound kept queued, in seconds
+const MAX_QUEUED_SECONDS: f32 = 0.2;
+
+pub struct AudioOutput {
+    queue: Arc<Mutex<VecDeque<i16>>>,
+    capacity: usize,
+    // Playback stops when the stream is dropped
+    _stream: Stream,
+}
+
+impl AudioOutput {
+    // Open the default output device at the emulator's sample rate
+    pub fn new(sample_rate: u32) -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let supported = device
+            .supported_output_configs()
+            .map_err(|e| e.to_string())?
+            .filter(|c| {
+                c.min_sample_rate().0 <= sample_rate && c.max_sample_rate().0 >= sample_rate
+            })
+            .find(|c| matches!(c.sample_format(), SampleFormat::F32 | SampleFormat::I16))
+            .ok_or_else(|| format!("output device does not support {} Hz", sample_rate))?
+            .with_sample_rate(cpal::SampleRate(sample_rate));
+
+        let capacity = (sample_rate as f32 * MAX_QUEUED_SECONDS) as usize;
+        let queue = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        let config = supported.config();
+        let stream = match supported.sample_format() {
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, Arc::clone(&queue)),
+            _ => build_stream::<f32>(&device, &config, Arc::clone(&queue)),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            queue,
+            capacity,
+            _stream: stream,
+        })
+    }
+
+    // Queue samples from the emulator, dropping the oldest ones when full
+    pub fn push(&mut self, samples: &[i16]) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.extend(samples);
+        let excess = queue.len().saturating_sub(self.capacity);
+        queue.drain(..excess);
+    }
+}
+
+// Output stream writing each queued mono sample to every channel
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    queue: Arc<Mutex<VecDeque<i16>>>,
+) -> Result<Stream, String>
+where
+    T: SizedSample + FromSample<i16>,
+{
+    let channels = config.channels as usize;
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                let mut queue = queue.lock().unwrap();
+                for frame in data.chunks_mut(channels) {
+                    let sample = T::from_sample(queue.pop_front().unwrap_or(0));
+                    frame.fill(sample);
+                }
+            },
+            |e| eprintln!("Audio error: {}", e),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,227 @@
+//! Cartridge images for the C1 and C2 expansion ROM slots
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+//!
+//! Two formats are accepted:
+//! - raw binaries of 16K (LO bank only) or 32K (LO followed by HI)
+//! - CRT containers as written by VICE for the C264 machines: a header
+//!   starting with "C264 CARTRIDGE" followed by CHIP packets, each placing
+//!   a ROM image at $8000 (LO) or $C000 (HI)
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result};
+use std::path::Path;
+
+use crate::plus4::ROM_BANK_SIZE;
+
+const CRT_SIGNATURE: &[u8] = b"C264 CARTRIDGE";
+const CHIP_SIGNATURE: &[u8] = b"CHIP";
+const CHIP_HEADER_LEN: usize = 16;
+
+/// Expansion slot a cartridge is plugged into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartridgeSlot {
+    C1,
+    C2,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cartridge {
+    pub name: String,
+    /// ROM for $8000-$BFFF, empty if not populated
+    pub low: Vec<u8>,
+    /// ROM for $C000-$FFFF, empty if not populated
+    pub high: Vec<u8>,
+}
+
+impl Cartridge {
+    /// Load a raw or CRT cartridge image from disk
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        let mut cartridge = Self::from_bytes(&buffer)?;
+        if cartridge.name.is_empty() {
+            if let Some(stem) = path.file_stem() {
+                cartridge.name = stem.to_string_lossy().into_owned();
+            }
+        }
+        Ok(cartridge)
+    }
+
+    /// Parse an image, detecting the format from its contents
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.starts_with(CRT_SIGNATURE) {
+            Self::from_crt(data)
+        } else {
+            Self::from_raw(data)
+        }
+    }
+
+    /// Raw 16K (LO) or 32K (LO + HI) image
+    pub fn from_raw(data: &[u8]) -> Result<Self> {
+        if data.len() != ROM_BANK_SIZE && data.len() != 2 * ROM_BANK_SIZE {
+            return Err(invalid_data(format!(
+                "cartridge image must be 16K or 32K, got {} bytes",
+                data.len()
+            )));
+        }
+        let (low, high) = data.split_at(ROM_BANK_SIZE);
+        Ok(Self {
+            name: String::new(),
+            low: low.to_vec(),
+            high: high.to_vec(),
+        })
+    }
+
+    /// CRT container with CHIP packets
+    pub fn from_crt(data: &[u8]) -> Result<Self> {
+        if data.len() < 0x40 || !data.starts_with(CRT_SIGNATURE) {
+            return Err(invalid_data("not a C264 CRT image".to_string()));
+        }
+        let header_len = read_u32(data, 0x10) as usize;
+        if header_len < 0x40 || header_len > data.len() {
+            return Err(invalid_data(format!("invalid CRT header length {}", header_len)));
+        }
+        let name = String::from_utf8_lossy(&data[0x20..0x40])
+            .trim_end_matches('\0')
+            .trim()
+            .to_string();
+
+        let mut cartridge = Self { name, low: Vec::new(), high: Vec::new() };
+        let mut offset = header_len;
+        while offset + CHIP_HEADER_LEN <= data.len() {
+            let packet = &data[offset..];
+            if !packet.starts_with(CHIP_SIGNATURE) {
+                return Err(invalid_data(format!("missing CHIP packet at offset {}", offset)));
+            }
+            let packet_len = read_u32(packet, 4) as usize;
+            let load_address = read_u16(packet, 0x0C);
+            let size = read_u16(packet, 0x0E) as usize;
+            if packet_len < CHIP_HEADER_LEN + size || CHIP_HEADER_LEN + size > packet.len() {
+                return Err(invalid_data(format!("truncated CHIP packet at offset {}", offset)));
+            }
+            cartridge.place_chip(load_address, &packet[CHIP_HEADER_LEN..CHIP_HEADER_LEN + size])?;
+            offset += packet_len;
+        }
+
+        if cartridge.low.is_empty() && cartridge.high.is_empty() {
+            return Err(invalid_data("CRT image contains no ROM".to_string()));
+        }
+        Ok(cartridge)
+    }
+
+    /// True if the KERNAL will start the cartridge at reset: "CBM" at
+    /// $8007 and module number 1 at $8006. The KERNAL then calls $8000
+    /// with the cartridge banked in.
+    pub fn has_autostart(&self) -> bool {
+        self.low.len() >= 10 && self.low[6] == 1 && &self.low[7..10] == b"CBM"
+    }
+
+    // Copy a CHIP image into the bank at its load address; a 32K chip at
+    // $8000 covers both banks
+    fn place_chip(&mut self, load_address: u16, rom: &[u8]) -> Result<()> {
+        match (load_address, rom.len()) {
+            (0x8000, len) if len <= ROM_BANK_SIZE => self.low = rom.to_vec(),
+            (0x8000, len) if len <= 2 * ROM_BANK_SIZE => {
+                self.low = rom[..ROM_BANK_SIZE].to_vec();
+                self.high = rom[ROM_BANK_SIZE..].to_vec();
+            }
+            (0xC000, len) if len <= ROM_BANK_SIZE => self.high = rom.to_vec(),
+            _ => {
+                return Err(invalid_data(format!(
+                    "unsupported CHIP at ${:04X} with {} bytes",
+                    load_address,
+                    rom.len()
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+// CRT fields are big endian
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crt_header(name: &str) -> Vec<u8> {
+        let mut data = vec![0u8; 0x40];
+        data[..16].copy_from_slice(b"C264 CARTRIDGE  ");
+        data[0x10..0x14].copy_from_slice(&0x40u32.to_be_bytes());
+        data[0x14..0x16].copy_from_slice(&0x0100u16.to_be_bytes());
+        data[0x20..0x20 + name.len()].copy_from_slice(name.as_bytes());
+        data
+    }
+
+    fn chip(load_address: u16, rom: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"CHIP");
+        data.extend_from_slice(&((CHIP_HEADER_LEN + rom.len()) as u32).to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&load_address.to_be_bytes());
+        data.extend_from_slice(&(rom.len() as u16).to_be_bytes());
+        data.extend_from_slice(rom);
+        data
+    }
+
+    #[test]
+    fn test_raw_images() {
+        let cartridge = Cartridge::from_bytes(&[0x11; ROM_BANK_SIZE]).unwrap();
+        assert_eq!(cartridge.low.len(), ROM_BANK_SIZE);
+        assert!(cartridge.high.is_empty());
+
+        let mut image = vec![0x11; ROM_BANK_SIZE];
+        image.extend_from_slice(&[0x22; ROM_BANK_SIZE]);
+        let cartridge = Cartridge::from_bytes(&image).unwrap();
+        assert_eq!(cartridge.high[0], 0x22);
+
+        let err = Cartridge::from_bytes(&[0; 1000]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_crt_image() {
+        let mut data = crt_header("DEMO CART");
+        data.extend(chip(0xC000, &[0x22; 0x2000]));
+        data.extend(chip(0x8000, &[0x11; 0x4000]));
+
+        let cartridge = Cartridge::from_bytes(&data).unwrap();
+        assert_eq!(cartridge.name, "DEMO CART");
+        assert_eq!(cartridge.low, vec![0x11; 0x4000]);
+        assert_eq!(cartridge.high, vec![0x22; 0x2000]);
+
+        data.truncate(data.len() - 1);
+        assert!(Cartridge::from_bytes(&data).is_err());
+        assert!(Cartridge::from_bytes(&crt_header("EMPTY")).is_err());
+    }
+
+    #[test]
+    fn test_autostart_signature() {
+        let mut rom = vec![0xEA; ROM_BANK_SIZE];
+        assert!(!Cartridge::from_raw(&rom).unwrap().has_autostart());
+        rom[6] = 1;
+        rom[7..10].copy_from_slice(b"CBM");
+        assert!(Cartridge::from_raw(&rom).unwrap().has_autostart());
+        rom[6] = 0;
+        assert!(!Cartridge::from_raw(&rom).unwrap().has_autostart());
+    }
+}
@@ -1,11 +1,11 @@
-/// CPU State for 6510 processor
-/// Copyright (C) 2009 Florian Wolff (florian@donuz.de)
-/// Rust port 2025
-///
-/// This program is free software; you can redistribute it and/or
-/// modify it under the terms of the GNU General Public License
-/// as published by the Free Software Foundation; either version 2
-/// of the License, or (at your option) any later version.
+//! CPU State for 6510 processor
+//! Copyright (C) 2009 Florian Wolff (florian@donuz.de)
+//! Rust port 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
 
 #[derive(Debug, Clone)]
 pub struct CpuState {
@@ -20,10 +20,15 @@ pub struct CpuState {
     pub c: bool,      // Carry
     pub z: bool,      // Zero
     pub n: bool,      // Negative
+    // Kept for existing users of the struct; never read or written
+    #[deprecated(note = "B only exists in the flags pushed by PHP and BRK; this field is ignored")]
     pub b: bool,      // Break
     pub v: bool,      // Overflow
     pub i: bool,      // Interrupt Disable
     pub d: bool,      // Decimal Mode
+
+    // Set by the JAM opcodes; the CPU stays halted until the next reset
+    pub jammed: bool,
 }
 
 impl Default for CpuState {
@@ -37,10 +42,12 @@ impl Default for CpuState {
             c: false,
             z: false,
             n: false,
+            #[allow(deprecated)]
             b: false,
             v: false,
             i: false,
             d: false,
+            jammed: false,
         }
     }
 }
@@ -52,7 +59,7 @@ impl CpuState {
 
     // Program Counter operations
     pub fn incr_pc(&mut self, inc: u16) {
-        self.pc = self.pc.wrapping_add(inc) & 0xFFFF;
+        self.pc = self.pc.wrapping_add(inc);
     }
 
     // Stack Pointer operations
@@ -78,30 +85,28 @@ impl CpuState {
         let old_acc = self.acc;
 
         if self.d {
-            // Decimal mode (BCD)
+            // Decimal mode (BCD), NMOS behaviour: Z comes from the binary sum,
+            // N and V from the sum after the low nibble fix-up
             let carry_in = if self.c { 1 } else { 0 };
 
             // Add low nibbles
             let mut low = (self.acc & 0x0F) + (data & 0x0F) + carry_in;
             if low > 0x09 {
-                low += 0x06;
+                low = ((low + 0x06) & 0x0F) + 0x10;
             }
 
             // Add high nibbles
-            let mut high = (self.acc >> 4) + (data >> 4) + if low > 0x0F { 1 } else { 0 };
+            let sum = (self.acc & 0xF0) as u16 + (data & 0xF0) as u16 + low as u16;
+            let signed_sum = (self.acc & 0xF0) as i8 as i16 + (data & 0xF0) as i8 as i16 + low as i16;
 
-            // Set flags based on binary result for compatibility
             let bin_result = (self.acc as u16) + (data as u16) + (carry_in as u16);
             self.z = (bin_result & 0xFF) == 0;
-            self.n = (bin_result & 0x80) != 0;
-            self.v = ((!(old_acc ^ data)) & (old_acc ^ bin_result as u8) & 0x80) != 0;
-
-            if high > 0x09 {
-                high += 0x06;
-            }
+            self.n = (sum & 0x80) != 0;
+            self.v = !(-128..=127).contains(&signed_sum);
 
-            self.c = high > 0x0F;
-            self.acc = ((high << 4) | (low & 0x0F)) & 0xFF;
+            let sum = if sum >= 0xA0 { sum + 0x60 } else { sum };
+            self.c = sum >= 0x100;
+            self.acc = sum as u8;
         } else {
             // Binary mode
             let raw = data as u16 + self.acc as u16 + if self.c { 1 } else { 0 };
@@ -200,34 +205,24 @@ impl CpuState {
         let old_acc = self.acc;
 
         if self.d {
-            // Decimal mode
-            let mut tmp_dec = (self.acc as i32 & 0x0F) - (data as i32 & 0x0F) - if self.c { 0 } else { 1 };
+            // Decimal mode, NMOS behaviour: all flags come from the binary result
+            let borrow = if self.c { 0 } else { 1 };
 
-            if (tmp_dec & 0x10) != 0 {
-                tmp_dec = ((tmp_dec - 6) & 0xF) | ((self.acc as i32 & 0xF0) - (data as i32 & 0xF0) - 0x10);
-            } else {
-                tmp_dec = (tmp_dec & 0xF) | ((self.acc as i32 & 0xF0) - (data as i32 & 0xF0));
+            let mut low = (self.acc & 0x0F) as i16 - (data & 0x0F) as i16 - borrow;
+            if low < 0 {
+                low = ((low - 0x06) & 0x0F) - 0x10;
             }
-            if (tmp_dec & 0x100) != 0 {
-                tmp_dec -= 0x60;
+            let mut result = (self.acc & 0xF0) as i16 - (data & 0xF0) as i16 + low;
+            if result < 0 {
+                result -= 0x60;
             }
 
-            self.acc = tmp_dec as u8;
-            self.c = tmp_dec < 0x100;
-
-            self.neg_flag(self.acc);
-            self.zero_flag(self.acc);
-
-            self.v = false;
-            if !self.n && (self.acc & 0x80) != 0 && (data & 0x80) != 0 && (old_acc & 0x80) == 0 {
-                self.v = true;
-            }
-            if !self.n && (self.acc & 0x80) == 0 && (data & 0x80) == 0 && (old_acc & 0x80) != 0 {
-                self.v = true;
-            }
-            if self.n && (self.acc & 0x80) != 0 && (data & 0x80) != 0 && (old_acc & 0x80) == 0 {
-                self.v = true;
-            }
+            let raw = self.acc as i16 - data as i16 - borrow;
+            self.c = raw >= 0;
+            self.neg_flag(raw as u8);
+            self.zero_flag(raw as u8);
+            self.v = ((old_acc ^ data) & (old_acc ^ raw as u8) & 0x80) != 0;
+            self.acc = result as u8;
         } else {
             // Binary mode
             let raw = self.acc as i32 - data as i32 - if self.c { 0 } else { 1 };
@@ -243,6 +238,103 @@ impl CpuState {
         }
     }
 
+    // LAX - Load Accumulator and X (undocumented)
+    pub fn do_lax(&mut self, data: u8) {
+        self.acc = data;
+        self.xr = data;
+        self.neg_flag(data);
+        self.zero_flag(data);
+    }
+
+    // ANC - AND, then copy bit 7 into Carry (undocumented)
+    pub fn do_anc(&mut self, data: u8) {
+        self.do_and(data);
+        self.c = self.n;
+    }
+
+    // ALR - AND, then Logical Shift Right (undocumented)
+    pub fn do_alr(&mut self, data: u8) {
+        let value = self.acc & data;
+        self.acc = self.do_lsr(value);
+    }
+
+    // ARR - AND, then Rotate Right with special Carry/Overflow (undocumented)
+    pub fn do_arr(&mut self, data: u8) {
+        let value = self.acc & data;
+        let mut result = value >> 1;
+        if self.c {
+            result |= 0x80;
+        }
+
+        if self.d {
+            // Decimal mode: N is the old carry, Z/V come from the binary result,
+            // then both nibbles get a BCD fix-up
+            self.n = self.c;
+            self.zero_flag(result);
+            self.v = ((value ^ result) & 0x40) != 0;
+
+            if (value & 0x0F) + (value & 0x01) > 0x05 {
+                result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
+            }
+            if (value as u16 & 0xF0) + (value as u16 & 0x10) > 0x50 {
+                result = (result & 0x0F) | (result.wrapping_add(0x60) & 0xF0);
+                self.c = true;
+            } else {
+                self.c = false;
+            }
+            self.acc = result;
+        } else {
+            self.acc = result;
+            self.neg_flag(result);
+            self.zero_flag(result);
+            self.c = (result & 0x40) != 0;
+            self.v = (((result >> 6) ^ (result >> 5)) & 1) != 0;
+        }
+    }
+
+    // SBX - X = (A AND X) - data, flags like CMP (undocumented)
+    pub fn do_sbx(&mut self, data: u8) {
+        let value = self.acc & self.xr;
+        self.c = value >= data;
+        self.xr = value.wrapping_sub(data);
+        self.neg_flag(self.xr);
+        self.zero_flag(self.xr);
+    }
+
+    // INC/DEC - Increment/Decrement memory value
+    pub fn do_inc(&mut self, data: u8) -> u8 {
+        let result = data.wrapping_add(1);
+        self.neg_flag(result);
+        self.zero_flag(result);
+        result
+    }
+
+    pub fn do_dec(&mut self, data: u8) -> u8 {
+        let result = data.wrapping_sub(1);
+        self.neg_flag(result);
+        self.zero_flag(result);
+        result
+    }
+
+    // Load operations
+    pub fn lda(&mut self, data: u8) {
+        self.acc = data;
+        self.neg_flag(self.acc);
+        self.zero_flag(self.acc);
+    }
+
+    pub fn ldx(&mut self, data: u8) {
+        self.xr = data;
+        self.neg_flag(self.xr);
+        self.zero_flag(self.xr);
+    }
+
+    pub fn ldy(&mut self, data: u8) {
+        self.yr = data;
+        self.neg_flag(self.yr);
+        self.zero_flag(self.yr);
+    }
+
     // Register operations
     pub fn dey(&mut self) {
         self.yr = self.yr.wrapping_sub(1);
@@ -303,3 +395,84 @@ impl CpuState {
         self.sp = self.xr;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bcd(value: u8) -> u8 {
+        ((value / 10) << 4) | (value % 10)
+    }
+
+    // Every pair of valid BCD operands against plain decimal arithmetic
+    #[test]
+    fn test_decimal_adc_valid_bcd() {
+        for a in 0..100u8 {
+            for b in 0..100u8 {
+                for c in [false, true] {
+                    let mut cpu = CpuState { acc: bcd(a), c, d: true, ..CpuState::new() };
+                    cpu.do_adc(bcd(b));
+                    let sum = a + b + c as u8;
+                    assert_eq!((cpu.acc, cpu.c), (bcd(sum % 100), sum >= 100),
+                               "ADC {}+{}+{}", a, b, c as u8);
+                    // Z comes from the binary sum on NMOS
+                    assert_eq!(cpu.z, bcd(a).wrapping_add(bcd(b)).wrapping_add(c as u8) == 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_sbc_valid_bcd() {
+        for a in 0..100i16 {
+            for b in 0..100i16 {
+                for c in [false, true] {
+                    let (da, db) = (bcd(a as u8), bcd(b as u8));
+                    let mut cpu = CpuState { acc: da, c, d: true, ..CpuState::new() };
+                    cpu.do_sbc(db);
+                    let difference = a - b - !c as i16;
+                    assert_eq!((cpu.acc, cpu.c), (bcd(difference.rem_euclid(100) as u8), difference >= 0),
+                               "SBC {}-{}-{}", a, b, !c as u8);
+                    // N, V and Z come from the binary difference on NMOS
+                    let binary = da.wrapping_sub(db).wrapping_sub(!c as u8);
+                    let signed = da as i8 as i16 - db as i8 as i16 - !c as i16;
+                    let overflow = !(-128..=127).contains(&signed);
+                    assert_eq!((cpu.n, cpu.v, cpu.z), (binary >= 0x80, overflow, binary == 0),
+                               "SBC {}-{}-{} flags", a, b, !c as u8);
+                }
+            }
+        }
+    }
+
+    // Known NMOS 6502 results for the decimal ADC flags and invalid BCD
+    // operands (Bruce Clark, "Decimal Mode", appendix A), as (A, C, N, V, Z)
+    #[test]
+    fn test_decimal_adc_known_answers() {
+        let cases = [
+            (0x99, 0x01, false, (0x00, true, true, false, false)),
+            (0x79, 0x00, true, (0x80, false, true, true, false)),
+            (0x24, 0x56, false, (0x80, false, true, true, false)),
+            (0x93, 0x82, false, (0x75, true, false, true, false)),
+            (0x89, 0x76, false, (0x65, true, false, false, false)),
+            (0x80, 0x80, false, (0x60, true, false, true, true)),
+            (0x1A, 0x00, false, (0x20, false, false, false, false)),
+        ];
+        for (a, b, c, expected) in cases {
+            let mut cpu = CpuState { acc: a, c, d: true, ..CpuState::new() };
+            cpu.do_adc(b);
+            assert_eq!((cpu.acc, cpu.c, cpu.n, cpu.v, cpu.z), expected,
+                       "ADC {:02X}+{:02X}+{}", a, b, c as u8);
+        }
+    }
+
+    #[test]
+    fn test_binary_adc_sbc_flags() {
+        let mut cpu = CpuState { acc: 0x7F, ..CpuState::new() };
+        cpu.do_adc(0x01);
+        assert_eq!((cpu.acc, cpu.c, cpu.n, cpu.v, cpu.z), (0x80, false, true, true, false));
+
+        let mut cpu = CpuState { acc: 0x00, c: true, ..CpuState::new() };
+        cpu.do_sbc(0x01);
+        assert_eq!((cpu.acc, cpu.c, cpu.n, cpu.v, cpu.z), (0xFF, false, true, false, false));
+    }
+}
@@ -0,0 +1,142 @@
+//! 6502/7501 disassembler
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+use std::fmt;
+
+use crate::opcodes::{AddressMode, Mnemonic, OPCODES};
+use crate::symbols::SymbolTable;
+
+/// One disassembled instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisassembledLine {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: Mnemonic,
+    pub undocumented: bool,
+    /// Operand in assembler syntax, e.g. `#$00`, `($FE),Y` or `$D88B`
+    pub operand: String,
+    /// Symbol at this instruction's address
+    pub label: Option<String>,
+    /// Symbol of the address the operand refers to
+    pub target_label: Option<String>,
+}
+
+impl DisassembledLine {
+    /// Address of the following instruction
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.bytes.len() as u16)
+    }
+}
+
+impl fmt::Display for DisassembledLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        let marker = if self.undocumented { "*" } else { " " };
+        let text = format!("{}{} {}", marker, self.mnemonic, self.operand);
+        write!(f, "{:04X}  {:<8}  {:<14}", self.address, bytes.join(" "), text.trim_end())?;
+
+        // Java listing style: description of the referenced address
+        let comment = self.target_label.as_deref().or(self.label.as_deref());
+        if let Some(comment) = comment {
+            write!(f, " ; {}", comment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Disassemble the instruction at `addr`, reading memory through `peek`
+pub fn disassemble_one<F: Fn(u16) -> u8>(peek: F, addr: u16, symbols: &SymbolTable) -> DisassembledLine {
+    let opcode = peek(addr);
+    let op = OPCODES[opcode as usize];
+    let bytes: Vec<u8> = (0..op.length()).map(|i| peek(addr.wrapping_add(i))).collect();
+
+    let byte = bytes.get(1).copied().unwrap_or(0);
+    let word = byte as u16 | ((bytes.get(2).copied().unwrap_or(0) as u16) << 8);
+
+    let (operand, target) = match op.mode {
+        AddressMode::Implied => (String::new(), None),
+        AddressMode::Accumulator => ("A".to_string(), None),
+        AddressMode::Immediate => (format!("#${:02X}", byte), None),
+        AddressMode::ZeroPage => (format!("${:02X}", byte), Some(byte as u16)),
+        AddressMode::ZeroPageX => (format!("${:02X},X", byte), Some(byte as u16)),
+        AddressMode::ZeroPageY => (format!("${:02X},Y", byte), Some(byte as u16)),
+        AddressMode::Absolute => (format!("${:04X}", word), Some(word)),
+        AddressMode::AbsoluteX => (format!("${:04X},X", word), Some(word)),
+        AddressMode::AbsoluteY => (format!("${:04X},Y", word), Some(word)),
+        AddressMode::IndirectX => (format!("(${:02X},X)", byte), Some(byte as u16)),
+        AddressMode::IndirectY => (format!("(${:02X}),Y", byte), Some(byte as u16)),
+        AddressMode::Indirect => (format!("(${:04X})", word), Some(word)),
+        AddressMode::Relative => {
+            let dest = addr.wrapping_add(2).wrapping_add(byte as i8 as u16);
+            (format!("${:04X}", dest), Some(dest))
+        }
+    };
+
+    DisassembledLine {
+        address: addr,
+        bytes,
+        mnemonic: op.mnemonic,
+        undocumented: op.undocumented,
+        operand,
+        label: symbols.get(addr).map(str::to_string),
+        target_label: target.and_then(|t| symbols.get(t)).map(str::to_string),
+    }
+}
+
+/// Disassemble `count` consecutive instructions starting at `addr`
+pub fn disassemble<F: Fn(u16) -> u8>(peek: F, addr: u16, count: usize, symbols: &SymbolTable) -> Vec<DisassembledLine> {
+    let mut lines = Vec::with_capacity(count);
+    let mut addr = addr;
+    for _ in 0..count {
+        let line = disassemble_one(&peek, addr, symbols);
+        addr = line.next_address();
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(program: &[u8]) -> impl Fn(u16) -> u8 + '_ {
+        move |addr| program.get(addr.wrapping_sub(0x1000) as usize).copied().unwrap_or(0)
+    }
+
+    #[test]
+    fn test_operand_formats() {
+        let program = [
+            0xA9, 0x00,             // LDA #$00
+            0xB1, 0xFE,             // LDA ($FE),Y
+            0x9D, 0x00, 0x0C,       // STA $0C00,X
+            0x0A,                   // ASL A
+            0x6C, 0xFE, 0xFF,       // JMP ($FFFE)
+            0xD0, 0xF3,             // BNE $1000
+            0xA7, 0x10,             // *LAX $10
+        ];
+        let lines = disassemble(memory(&program), 0x1000, 7, &SymbolTable::new());
+        let text: Vec<String> = lines.iter().map(|l| format!("{} {}", l.mnemonic, l.operand)).collect();
+        assert_eq!(text, vec![
+            "LDA #$00", "LDA ($FE),Y", "STA $0C00,X", "ASL A", "JMP ($FFFE)", "BNE $1000", "LAX $10",
+        ]);
+        assert_eq!(lines[2].bytes, vec![0x9D, 0x00, 0x0C]);
+        assert!(lines[6].undocumented);
+        assert_eq!(lines[6].next_address(), 0x100F);
+    }
+
+    #[test]
+    fn test_labels() {
+        let program = [0x20, 0x8B, 0xD8]; // JSR $D88B
+        let mut symbols = SymbolTable::plus4_rom();
+        symbols.insert(0x1000, "start");
+        let line = disassemble_one(memory(&program), 0x1000, &symbols);
+        assert_eq!(line.label.as_deref(), Some("start"));
+        assert_eq!(line.target_label.as_deref(), Some("CLEAR SCREEN"));
+        assert_eq!(line.to_string(), "1000  20 8B D8   JSR $D88B     ; CLEAR SCREEN");
+    }
+}
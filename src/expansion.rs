@@ -0,0 +1,118 @@
+//! RAM expansions for the Plus/4
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+//!
+//! The Hannes expansions replace the 64K RAM with banks of 64K. The first
+//! 4K (zero page, stack and system variables) always come from bank 0, so
+//! the KERNAL keeps working whichever bank is selected.
+//!
+//! Hannes (256K or 1M), bank register at $FD16:
+//! - bits 0-1: bank seen by the CPU; on 1M, bits 4-5 are the upper bits
+//! - bits 2-3: bank TED fetches screen, color and bitmap data from
+//!
+//! The Csory 256K board is not emulated: its bank select through the
+//! $FF3E/$FF3F strobes is not documented anywhere we could check.
+
+/// RAM below this address is shared by all banks
+pub const COMMON_RAM_END: usize = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RamExpansion {
+    #[default]
+    None,
+    Hannes256K,
+    Hannes1M,
+}
+
+impl RamExpansion {
+    pub const ALL: [RamExpansion; 3] = [
+        RamExpansion::None,
+        RamExpansion::Hannes256K,
+        RamExpansion::Hannes1M,
+    ];
+
+    /// Name as used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            RamExpansion::None => "none",
+            RamExpansion::Hannes256K => "hannes256k",
+            RamExpansion::Hannes1M => "hannes1m",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|expansion| expansion.name() == name)
+    }
+
+    /// Number of 64K banks
+    pub fn banks(self) -> usize {
+        match self {
+            RamExpansion::None => 1,
+            RamExpansion::Hannes256K => 4,
+            RamExpansion::Hannes1M => 16,
+        }
+    }
+
+    /// Address of the bank select register
+    pub fn register(self) -> Option<usize> {
+        match self {
+            RamExpansion::None => None,
+            RamExpansion::Hannes256K | RamExpansion::Hannes1M => Some(0xFD16),
+        }
+    }
+
+    /// Bank the CPU sees at `addr` for a bank register value
+    pub fn cpu_bank(self, register: u8, addr: usize) -> usize {
+        if addr < COMMON_RAM_END {
+            return 0;
+        }
+        let register = register as usize;
+        match self {
+            RamExpansion::None => 0,
+            RamExpansion::Hannes256K => register & 3,
+            RamExpansion::Hannes1M => (register & 3) | ((register >> 2) & 0x0C),
+        }
+    }
+
+    /// Bank TED fetches video data from at `addr`
+    pub fn ted_bank(self, register: u8, addr: usize) -> usize {
+        match self {
+            RamExpansion::Hannes256K | RamExpansion::Hannes1M if addr >= COMMON_RAM_END => {
+                (register as usize >> 2) & 3
+            }
+            _ => self.cpu_bank(register, addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expansion_names() {
+        for expansion in RamExpansion::ALL {
+            assert_eq!(RamExpansion::from_name(expansion.name()), Some(expansion));
+        }
+        assert_eq!(RamExpansion::from_name("Hannes256K"), Some(RamExpansion::Hannes256K));
+        assert_eq!(RamExpansion::from_name("4m"), None);
+    }
+
+    #[test]
+    fn test_hannes_banks() {
+        let hannes = RamExpansion::Hannes256K;
+        assert_eq!(hannes.cpu_bank(0x0E, 0x2000), 2);
+        assert_eq!(hannes.ted_bank(0x0E, 0x2000), 3);
+        assert_eq!(hannes.cpu_bank(0x0E, 0x0FFF), 0);
+        assert_eq!(hannes.ted_bank(0x0E, 0x0800), 0);
+
+        let hannes = RamExpansion::Hannes1M;
+        assert_eq!(hannes.cpu_bank(0x31, 0x8000), 13);
+        assert_eq!(hannes.ted_bank(0x31, 0x8000), 0);
+    }
+}
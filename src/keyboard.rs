@@ -1,11 +1,11 @@
-/// Keyboard matrix mapping for Plus/4
-/// Copyright (C) 2009 Florian Wolff (florian@donuz.de)
-/// Rust port 2025
-///
-/// This program is free software; you can redistribute it and/or
-/// modify it under the terms of the GNU General Public License
-/// as published by the Free Software Foundation; either version 2
-/// of the License, or (at your option) any later version.
+//! Keyboard matrix mapping for Plus/4
+//! Copyright (C) 2009 Florian Wolff (florian@donuz.de)
+//! Rust port 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
 
 use macroquad::prelude::*;
 
@@ -117,6 +117,7 @@ impl KeyboardMatrix {
 
     /// Read keyboard matrix for a given latch value
     /// Returns the column bits for the selected row(s)
+    #[allow(dead_code)]
     pub fn read(&self, latch: u8) -> u8 {
         let mut result = 0xFFu8;
 
@@ -0,0 +1,35 @@
+//! Plus/4 Emulator core library
+//! Copyright (C) 2009 Florian Wolff (florian@donuz.de)
+//! Rust port 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+//!
+//! The emulator core has no UI dependencies and can be embedded in test
+//! tools and batch jobs. The macroquad frontend lives in the `plus4emu`
+//! binary behind the `gui` feature.
+
+pub mod cartridge;
+pub mod cpu_state;
+pub mod disassembler;
+pub mod expansion;
+pub mod machine;
+pub mod opcodes;
+pub mod plus4;
+pub mod prg_loader;
+pub mod roms;
+pub mod sound;
+pub mod symbols;
+pub mod timers;
+pub mod trace;
+pub mod wav;
+
+pub use cartridge::{Cartridge, CartridgeSlot};
+pub use cpu_state::CpuState;
+pub use expansion::RamExpansion;
+pub use machine::MachineModel;
+pub use plus4::Plus4;
+pub use prg_loader::PrgFile;
+pub use roms::{RomPaths, RomSet};
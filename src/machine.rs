@@ -0,0 +1,120 @@
+//! Machine models of the TED family
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+use crate::plus4::{DOUBLE_CLOCK_FREQUENCY, FRAME_HEIGHT, RASTER_LINES, TICKS_PER_RASTER_LINE};
+
+// NTSC double clock (14.31818 MHz / 8) and line count
+pub const NTSC_DOUBLE_CLOCK_FREQUENCY: u32 = 1_789_773;
+pub const NTSC_RASTER_LINES: u32 = 262;
+
+/// Hardware configuration of the emulated machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MachineModel {
+    /// 16K RAM, no function ROM, PAL
+    C16,
+    /// C16 in the small case with rubber keys: 16K RAM, no function ROM, PAL
+    C116,
+    /// 64K RAM with the 3-plus-1 function ROM, PAL
+    #[default]
+    Plus4Pal,
+    /// 64K RAM with the 3-plus-1 function ROM, NTSC
+    Plus4Ntsc,
+}
+
+impl MachineModel {
+    pub const ALL: [MachineModel; 4] = [
+        MachineModel::C16,
+        MachineModel::C116,
+        MachineModel::Plus4Pal,
+        MachineModel::Plus4Ntsc,
+    ];
+
+    /// Name as used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            MachineModel::C16 => "c16",
+            MachineModel::C116 => "c116",
+            MachineModel::Plus4Pal => "plus4",
+            MachineModel::Plus4Ntsc => "plus4-ntsc",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|model| model.name() == name)
+    }
+
+    /// Installed RAM in bytes. Smaller RAM is mirrored across the 64K
+    /// address space.
+    pub fn ram_size(self) -> usize {
+        match self {
+            MachineModel::C16 | MachineModel::C116 => 0x4000,
+            MachineModel::Plus4Pal | MachineModel::Plus4Ntsc => 0x10000,
+        }
+    }
+
+    pub fn has_function_rom(self) -> bool {
+        matches!(self, MachineModel::Plus4Pal | MachineModel::Plus4Ntsc)
+    }
+
+    pub fn is_ntsc(self) -> bool {
+        self == MachineModel::Plus4Ntsc
+    }
+
+    /// TED double clock, which also drives the sound generator
+    pub fn double_clock_frequency(self) -> u32 {
+        if self.is_ntsc() { NTSC_DOUBLE_CLOCK_FREQUENCY } else { DOUBLE_CLOCK_FREQUENCY }
+    }
+
+    /// CPU clock in single-clock mode
+    pub fn clock_frequency(self) -> u32 {
+        self.double_clock_frequency() / 2
+    }
+
+    pub fn raster_lines(self) -> u32 {
+        if self.is_ntsc() { NTSC_RASTER_LINES } else { RASTER_LINES }
+    }
+
+    /// Visible lines of the frame: the PAL frame, cut off at the bottom
+    /// where the NTSC raster runs out of lines
+    pub fn frame_height(self) -> usize {
+        FRAME_HEIGHT.min(self.raster_lines() as usize)
+    }
+
+    /// Frames per second
+    pub fn frame_rate(self) -> f32 {
+        self.double_clock_frequency() as f32 / (self.raster_lines() * TICKS_PER_RASTER_LINE) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_names() {
+        for model in MachineModel::ALL {
+            assert_eq!(MachineModel::from_name(model.name()), Some(model));
+        }
+        assert_eq!(MachineModel::from_name("C16"), Some(MachineModel::C16));
+        assert_eq!(MachineModel::from_name("vic20"), None);
+    }
+
+    #[test]
+    fn test_frame_rates() {
+        assert!((MachineModel::Plus4Pal.frame_rate() - 49.86).abs() < 0.01);
+        assert!((MachineModel::Plus4Ntsc.frame_rate() - 59.92).abs() < 0.01);
+        assert_eq!(MachineModel::C16.frame_rate(), MachineModel::Plus4Pal.frame_rate());
+    }
+
+    #[test]
+    fn test_frame_heights() {
+        assert_eq!(MachineModel::Plus4Pal.frame_height(), FRAME_HEIGHT);
+        assert_eq!(MachineModel::Plus4Ntsc.frame_height(), NTSC_RASTER_LINES as usize);
+    }
+}
@@ -1,25 +1,33 @@
-/// Plus/4 Emulator in Rust with macroquad
-/// Copyright (C) 2009 Florian Wolff (florian@donuz.de)
-/// Rust port 2025
-///
-/// This program is free software; you can redistribute it and/or
-/// modify it under the terms of the GNU General Public License
-/// as published by the Free Software Foundation; either version 2
-/// of the License, or (at your option) any later version.
-
-mod cpu_state;
-mod plus4;
+//! Plus/4 Emulator in Rust with macroquad
+//! Copyright (C) 2009 Florian Wolff (florian@donuz.de)
+//! Rust port 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
 mod screen;
 mod keyboard;
-mod prg_loader;
+#[cfg(feature = "audio")]
+mod audio;
 
 use macroquad::prelude::*;
-use plus4::{Plus4, SCREEN_WIDTH, SCREEN_HEIGHT};
-use screen::Screen;
+use plus4emu::plus4::{self, Plus4};
+use screen::{Screen, View};
 use keyboard::KeyboardMatrix;
-use prg_loader::PrgFile;
+#[cfg(feature = "audio")]
+use audio::AudioOutput;
+use plus4emu::expansion::RamExpansion;
+use plus4emu::machine::MachineModel;
+use plus4emu::prg_loader::PrgFile;
+use plus4emu::roms::{RomPaths, RomSet};
+use plus4emu::cartridge::{Cartridge, CartridgeSlot};
+use plus4emu::trace::{ExecutionFilter, TraceFilter, Tracer};
 
 const SCALE: f32 = 3.0;
+// ROM configuration read at startup when --config is not given
+const DEFAULT_CONFIG: &str = "plus4emu.cfg";
 
 // Create a simple test PRG for testing
 // This creates a minimal BASIC program: 10 PRINT "HELLO"
@@ -63,11 +71,111 @@ fn create_test_prg() -> PrgFile {
     PrgFile::from_data(0x1001, data)
 }
 
+// Value following a command line option, e.g. `--trace <file>`
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let pos = args.iter().position(|a| a == name)?;
+    args.get(pos + 1).map(|value| value.as_str())
+}
+
+fn parse_hex_address(text: &str) -> Option<u16> {
+    u16::from_str_radix(text.trim_start_matches('$').trim_start_matches("0x"), 16).ok()
+}
+
+// Trace filter from --trace-range <from>-<to> (repeatable), --trace-rom,
+// --trace-ram, --trace-start <addr> and --trace-stop <addr>
+fn trace_filter_from_args(args: &[String]) -> TraceFilter {
+    let mut filter = TraceFilter::default();
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--trace-range" {
+            let range = args.get(i + 1).and_then(|r| r.split_once('-'))
+                .and_then(|(from, to)| Some(parse_hex_address(from)?..=parse_hex_address(to)?));
+            match range {
+                Some(range) => filter.address_ranges.push(range),
+                None => println!("--trace-range expects <from>-<to>, e.g. 1000-1FFF"),
+            }
+        }
+    }
+
+    if args.iter().any(|a| a == "--trace-rom") {
+        filter.execution = ExecutionFilter::RomOnly;
+    }
+    if args.iter().any(|a| a == "--trace-ram") {
+        filter.execution = ExecutionFilter::RamOnly;
+    }
+    filter.start_trigger = arg_value(args, "--trace-start").and_then(parse_hex_address);
+    filter.stop_trigger = arg_value(args, "--trace-stop").and_then(parse_hex_address);
+    filter
+}
+
+// Machine model from --model <c16|c116|plus4|plus4-ntsc>, Plus/4 PAL by default
+fn model_from_args(args: &[String]) -> MachineModel {
+    match arg_value(args, "--model") {
+        Some(name) => MachineModel::from_name(name).unwrap_or_else(|| {
+            let names: Vec<&str> = MachineModel::ALL.iter().map(|model| model.name()).collect();
+            println!("Unknown model {}, expected one of {}", name, names.join(", "));
+            std::process::exit(1);
+        }),
+        None => MachineModel::default(),
+    }
+}
+
+// RAM expansion from --ram-expansion <hannes256k|hannes1m>
+fn expansion_from_args(args: &[String]) -> RamExpansion {
+    match arg_value(args, "--ram-expansion") {
+        Some(name) => RamExpansion::from_name(name).unwrap_or_else(|| {
+            let names: Vec<&str> = RamExpansion::ALL.iter().map(|expansion| expansion.name()).collect();
+            println!("Unknown RAM expansion {}, expected one of {}", name, names.join(", "));
+            std::process::exit(1);
+        }),
+        None => RamExpansion::None,
+    }
+}
+
+// ROM images from --config <file> (default plus4emu.cfg, if present),
+// overridden by --basic, --kernal, --function-lo, --function-hi <file>
+// and --no-function-rom. Models without function ROM never get one.
+fn rom_paths_from_args(args: &[String], model: MachineModel) -> std::io::Result<RomPaths> {
+    let config = match arg_value(args, "--config") {
+        Some(path) => RomPaths::load_config(path)?,
+        None if std::path::Path::new(DEFAULT_CONFIG).exists() => RomPaths::load_config(DEFAULT_CONFIG)?,
+        None => RomPaths::default(),
+    };
+    let path = |name| arg_value(args, name).map(std::path::PathBuf::from);
+    Ok(config.merge(RomPaths {
+        basic: path("--basic"),
+        kernal: path("--kernal"),
+        function_low: path("--function-lo"),
+        function_high: path("--function-hi"),
+        no_function_rom: !model.has_function_rom() || args.iter().any(|a| a == "--no-function-rom"),
+    }))
+}
+
+// --cropped shows only the 320x200 display window instead of the full
+// frame with its border
+fn view_from_args(args: &[String]) -> View {
+    if args.iter().any(|a| a == "--cropped") {
+        View::Cropped
+    } else {
+        View::FullFrame
+    }
+}
+
+fn dump_trace(tracer: &Tracer) {
+    println!("=== Last traced instructions ===");
+    for entry in tracer.entries() {
+        println!("{}", entry);
+    }
+}
+
 fn window_conf() -> Conf {
+    let args: Vec<String> = std::env::args().collect();
+    let view = view_from_args(&args);
+    let frame_height = model_from_args(&args).frame_height();
     Conf {
         window_title: "Plus/4 Emulator (Rust)".to_owned(),
-        window_width: (SCREEN_WIDTH as f32 * SCALE) as i32,
-        window_height: (SCREEN_HEIGHT as f32 * SCALE) as i32,
+        window_width: (view.width() as f32 * SCALE) as i32,
+        window_height: (view.height(frame_height) as f32 * SCALE) as i32,
         window_resizable: false,
         ..Default::default()
     }
@@ -75,24 +183,101 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
-    // Load ROM files
-    let rom_data = include_bytes!("../roms/rom.bin");
-    let rom3plus1_data = include_bytes!("../roms/3plus1.bin");
+    let args: Vec<String> = std::env::args().collect();
+    let model = model_from_args(&args);
+    println!("Machine: {}", model.name());
+
+    // ROM set from the config file and the command line, built-in ROMs
+    // for anything not given
+    let roms = match rom_paths_from_args(&args, model).and_then(|paths| RomSet::load(&paths)) {
+        Ok(roms) => roms,
+        Err(e) => {
+            println!("Error loading ROMs: {}", e);
+            std::process::exit(1);
+        }
+    };
+    for line in roms.describe() {
+        println!("ROM: {}", line);
+    }
+    if model.is_ntsc() && !roms.has_ntsc_kernal() {
+        println!("Warning: the KERNAL is not a known NTSC revision and sets the machine up for PAL; give an NTSC KERNAL with --kernal");
+    }
 
     // Initialize emulator
-    let mut emulator = Plus4::new();
-    emulator.load_rom(rom_data, rom3plus1_data);
+    let mut emulator = Plus4::with_model(model);
+    let expansion = expansion_from_args(&args);
+    if expansion != RamExpansion::None {
+        println!("RAM expansion: {}", expansion.name());
+        emulator.set_ram_expansion(expansion);
+    }
+    emulator.load_rom_set(&roms);
+
+    // Optional cartridges, raw or CRT: --cart1 <file> and --cart2 <file>
+    for (option, slot) in [("--cart1", CartridgeSlot::C1), ("--cart2", CartridgeSlot::C2)] {
+        if let Some(path) = arg_value(&args, option) {
+            match Cartridge::load_from_file(path) {
+                Ok(cartridge) => {
+                    println!("Inserted cartridge {} into {:?}{}", cartridge.name, slot,
+                             if cartridge.has_autostart() { " (autostart)" } else { "" });
+                    emulator.insert_cartridge(slot, &cartridge);
+                }
+                Err(e) => println!("Error loading cartridge {}: {}", path, e),
+            }
+        }
+    }
     emulator.hard_reset();
 
+    // Optional user symbol file for the disassembler: --symbols <file>
+    if let Some(path) = arg_value(&args, "--symbols") {
+        match emulator.load_symbols(path) {
+            Ok(count) => println!("Loaded {} symbols", count),
+            Err(e) => println!("Error loading symbols: {}", e),
+        }
+    }
+
+    // Optional instruction trace from power-on, to a file (--trace <file>)
+    // or into a ring buffer that is dumped on F8 and on CPU jams (--trace-buffer <n>)
+    let trace_filter = trace_filter_from_args(&args);
+    if let Some(path) = arg_value(&args, "--trace") {
+        match Tracer::to_file(path) {
+            Ok(tracer) => emulator.tracer = Some(tracer.with_filter(trace_filter.clone())),
+            Err(e) => println!("Error creating trace file: {}", e),
+        }
+    } else if let Some(size) = arg_value(&args, "--trace-buffer") {
+        match size.parse() {
+            Ok(size) => emulator.tracer = Some(Tracer::ring_buffer(size).with_filter(trace_filter.clone())),
+            Err(_) => println!("--trace-buffer expects a number of instructions"),
+        }
+    }
+    let mut jam_reported = false;
+
+    // Optional audio recording from power-on: --record <file.wav>
+    if let Some(path) = arg_value(&args, "--record") {
+        match emulator.start_recording(path) {
+            Ok(()) => println!("Recording audio to {}", path),
+            Err(e) => println!("Error creating {}: {}", path, e),
+        }
+    }
+
     // Initialize screen
-    let mut screen = Screen::new();
+    let mut screen = Screen::new(view_from_args(&args), emulator.frame_height());
 
     // Initialize keyboard
     let mut keyboard = KeyboardMatrix::new();
 
-    // Emulation state
-    let cycles_per_frame = plus4::CLOCK_FREQUENCY / 60;
-    let mut accumulated_cycles;
+    // Initialize audio; the emulator keeps running silently without it
+    #[cfg(feature = "audio")]
+    let mut audio = match AudioOutput::new(emulator.audio_sample_rate()) {
+        Ok(audio) => Some(audio),
+        Err(e) => {
+            eprintln!("Sound output is off: {}", e);
+            None
+        }
+    };
+
+    // Emulation state: time not yet emulated, in seconds
+    let mut pending_time = 0.0;
+    let frame_time = 1.0 / emulator.frame_rate();
 
     // PRG loading state
     let mut prg_loaded = false;
@@ -100,6 +285,13 @@ async fn main() {
     println!("Plus/4 Emulator started!");
     println!("Press ESC to exit");
     println!("Press F12 to load test.prg");
+    println!("Press F10 to disassemble at PC");
+    println!("Press F9 to start/stop tracing to trace.log");
+    println!("Press F8 to dump the trace buffer");
+
+    // Sound output needs the audio feature (see README.md)
+    #[cfg(not(feature = "audio"))]
+    println!("Sound output is off in this build; run with --features audio to enable it (needs the ALSA development files on Linux)");
 
     loop {
         // Input handling
@@ -137,6 +329,54 @@ async fn main() {
             }
         }
 
+        // F10: Disassemble the next instructions at PC
+        if is_key_pressed(KeyCode::F10) {
+            for line in emulator.disassemble(emulator.cpu.pc, 16) {
+                println!("{}", line);
+            }
+        }
+
+        // F9: Toggle instruction trace to trace.log
+        if is_key_pressed(KeyCode::F9) {
+            match emulator.tracer.take() {
+                Some(mut tracer) => {
+                    if let Err(e) = tracer.flush() {
+                        println!("Error writing trace: {}", e);
+                    }
+                    println!("Trace stopped");
+                }
+                None => match Tracer::to_file("trace.log") {
+                    Ok(tracer) => {
+                        emulator.tracer = Some(tracer.with_filter(trace_filter.clone()));
+                        println!("Tracing to trace.log");
+                    }
+                    Err(e) => println!("Error creating trace.log: {}", e),
+                },
+            }
+        }
+
+        // F7: Toggle audio recording to recording.wav
+        if is_key_pressed(KeyCode::F7) {
+            if emulator.is_recording() {
+                match emulator.stop_recording() {
+                    Ok(seconds) => println!("Recording stopped ({:.1} s)", seconds),
+                    Err(e) => println!("Error writing recording: {}", e),
+                }
+            } else {
+                match emulator.start_recording("recording.wav") {
+                    Ok(()) => println!("Recording audio to recording.wav"),
+                    Err(e) => println!("Error creating recording.wav: {}", e),
+                }
+            }
+        }
+
+        // F8: Dump the trace ring buffer
+        if is_key_pressed(KeyCode::F8) {
+            if let Some(tracer) = emulator.tracer.as_ref() {
+                dump_trace(tracer);
+            }
+        }
+
         // R key: Reset emulator
         if is_key_pressed(KeyCode::F11) {
             println!("Resetting emulator...");
@@ -144,17 +384,22 @@ async fn main() {
             prg_loaded = false;
         }
 
-        // Emulation loop - execute instructions until we've done enough for one frame
-        accumulated_cycles = 0;
-        while accumulated_cycles < cycles_per_frame {
-            emulator.step();
-            accumulated_cycles += emulator.clock_ticks;
+        // Emulation loop - run whole frames to keep up with real time,
+        // but never more than two per displayed frame
+        pending_time = (pending_time + get_frame_time()).min(2.0 * frame_time);
+        while pending_time >= frame_time {
+            emulator.run_frame();
+            pending_time -= frame_time;
+        }
 
-            // Limit to prevent infinite loop on errors
-            if accumulated_cycles > cycles_per_frame * 2 {
-                break;
-            }
+        // Play the sound produced during those frames
+        let samples = emulator.take_audio_samples();
+        #[cfg(feature = "audio")]
+        if let Some(audio) = audio.as_mut() {
+            audio.push(&samples);
         }
+        #[cfg(not(feature = "audio"))]
+        drop(samples);
 
         // Update screen with emulator's pixel buffer
         screen.update(&emulator.pixels);
@@ -183,6 +428,29 @@ async fn main() {
             WHITE,
         );
 
+        if emulator.is_recording() {
+            draw_text("REC", 150.0, 20.0, 20.0, RED);
+        }
+
+        // Show how the CPU got to a JAM opcode
+        if emulator.cpu.jammed && !jam_reported {
+            if let Some(tracer) = emulator.tracer.as_ref() {
+                dump_trace(tracer);
+            }
+        }
+        jam_reported = emulator.cpu.jammed;
+
+        // A JAM opcode halts the CPU until the next reset
+        if emulator.cpu.jammed {
+            draw_text(
+                &format!("CPU JAMMED at ${:04X} - press F11 to reset", emulator.cpu.pc),
+                10.0,
+                60.0,
+                20.0,
+                RED,
+            );
+        }
+
         // if prg_loaded {
         //     draw_text(
         //         "PRG Loaded",
@@ -204,5 +472,18 @@ async fn main() {
         next_frame().await;
     }
 
+    if let Some(tracer) = emulator.tracer.as_mut() {
+        if let Err(e) = tracer.flush() {
+            println!("Error writing trace: {}", e);
+        }
+    }
+
+    if emulator.is_recording() {
+        match emulator.stop_recording() {
+            Ok(seconds) => println!("Recording stopped ({:.1} s)", seconds),
+            Err(e) => println!("Error writing recording: {}", e),
+        }
+    }
+
     println!("Emulator stopped.");
 }
@@ -0,0 +1,486 @@
+//! 6502/7501 opcode table
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+use std::fmt;
+
+/// Addressing modes of the 6502/7501
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    IndirectX,      // (zp,X)
+    IndirectY,      // (zp),Y
+    Indirect,       // JMP (abs)
+    Relative,       // Branches
+}
+
+impl AddressMode {
+    /// Number of operand bytes following the opcode
+    pub fn operand_length(self) -> u16 {
+        match self {
+            AddressMode::Implied | AddressMode::Accumulator => 0,
+            AddressMode::Absolute
+            | AddressMode::AbsoluteX
+            | AddressMode::AbsoluteY
+            | AddressMode::Indirect => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Instruction mnemonics, including the undocumented NMOS opcodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mnemonic {
+    Adc, Alr, Anc, And, Ane, Arr, Asl, Bcc, Bcs, Beq, Bit, Bmi,
+    Bne, Bpl, Brk, Bvc, Bvs, Clc, Cld, Cli, Clv, Cmp, Cpx, Cpy,
+    Dcp, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Isc, Jam, Jmp, Jsr,
+    Las, Lax, Lda, Ldx, Ldy, Lsr, Lxa, Nop, Ora, Pha, Php, Pla,
+    Plp, Rla, Rol, Ror, Rra, Rti, Rts, Sax, Sbc, Sbx, Sec, Sed,
+    Sei, Sha, Shx, Shy, Slo, Sre, Sta, Stx, Sty, Tas, Tax, Tay,
+    Tsx, Txa, Txs, Tya,
+}
+
+impl Mnemonic {
+    pub fn as_str(self) -> &'static str {
+        use Mnemonic::*;
+        match self {
+            Adc => "ADC",
+            Alr => "ALR",
+            Anc => "ANC",
+            And => "AND",
+            Ane => "ANE",
+            Arr => "ARR",
+            Asl => "ASL",
+            Bcc => "BCC",
+            Bcs => "BCS",
+            Beq => "BEQ",
+            Bit => "BIT",
+            Bmi => "BMI",
+            Bne => "BNE",
+            Bpl => "BPL",
+            Brk => "BRK",
+            Bvc => "BVC",
+            Bvs => "BVS",
+            Clc => "CLC",
+            Cld => "CLD",
+            Cli => "CLI",
+            Clv => "CLV",
+            Cmp => "CMP",
+            Cpx => "CPX",
+            Cpy => "CPY",
+            Dcp => "DCP",
+            Dec => "DEC",
+            Dex => "DEX",
+            Dey => "DEY",
+            Eor => "EOR",
+            Inc => "INC",
+            Inx => "INX",
+            Iny => "INY",
+            Isc => "ISC",
+            Jam => "JAM",
+            Jmp => "JMP",
+            Jsr => "JSR",
+            Las => "LAS",
+            Lax => "LAX",
+            Lda => "LDA",
+            Ldx => "LDX",
+            Ldy => "LDY",
+            Lsr => "LSR",
+            Lxa => "LXA",
+            Nop => "NOP",
+            Ora => "ORA",
+            Pha => "PHA",
+            Php => "PHP",
+            Pla => "PLA",
+            Plp => "PLP",
+            Rla => "RLA",
+            Rol => "ROL",
+            Ror => "ROR",
+            Rra => "RRA",
+            Rti => "RTI",
+            Rts => "RTS",
+            Sax => "SAX",
+            Sbc => "SBC",
+            Sbx => "SBX",
+            Sec => "SEC",
+            Sed => "SED",
+            Sei => "SEI",
+            Sha => "SHA",
+            Shx => "SHX",
+            Shy => "SHY",
+            Slo => "SLO",
+            Sre => "SRE",
+            Sta => "STA",
+            Stx => "STX",
+            Sty => "STY",
+            Tas => "TAS",
+            Tax => "TAX",
+            Tay => "TAY",
+            Tsx => "TSX",
+            Txa => "TXA",
+            Txs => "TXS",
+            Tya => "TYA",
+        }
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One entry of the decoder table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opcode {
+    pub mnemonic: Mnemonic,
+    pub mode: AddressMode,
+    /// Base cycle count without page-crossing or branch penalties
+    pub cycles: u8,
+    /// Takes one extra cycle when indexing crosses a page boundary
+    pub page_penalty: bool,
+    /// Not part of the documented instruction set
+    pub undocumented: bool,
+}
+
+impl Opcode {
+    /// Instruction length in bytes, including the opcode
+    pub fn length(&self) -> u16 {
+        1 + self.mode.operand_length()
+    }
+}
+
+const PAGE: u8 = 1;
+const UNDOC: u8 = 2;
+
+const fn op(mnemonic: Mnemonic, mode: AddressMode, cycles: u8, flags: u8) -> Opcode {
+    Opcode {
+        mnemonic,
+        mode,
+        cycles,
+        page_penalty: (flags & PAGE) != 0,
+        undocumented: (flags & UNDOC) != 0,
+    }
+}
+
+use AddressMode::*;
+use Mnemonic::*;
+
+/// Decoder table indexed by opcode byte, shared by the CPU and the disassembler
+pub static OPCODES: [Opcode; 256] = [
+    op(Brk, Implied, 7, 0),                 // $00
+    op(Ora, IndirectX, 6, 0),               // $01
+    op(Jam, Implied, 2, UNDOC),             // $02
+    op(Slo, IndirectX, 8, UNDOC),           // $03
+    op(Nop, ZeroPage, 3, UNDOC),            // $04
+    op(Ora, ZeroPage, 3, 0),                // $05
+    op(Asl, ZeroPage, 5, 0),                // $06
+    op(Slo, ZeroPage, 5, UNDOC),            // $07
+    op(Php, Implied, 3, 0),                 // $08
+    op(Ora, Immediate, 2, 0),               // $09
+    op(Asl, Accumulator, 2, 0),             // $0A
+    op(Anc, Immediate, 2, UNDOC),           // $0B
+    op(Nop, Absolute, 4, UNDOC),            // $0C
+    op(Ora, Absolute, 4, 0),                // $0D
+    op(Asl, Absolute, 6, 0),                // $0E
+    op(Slo, Absolute, 6, UNDOC),            // $0F
+    op(Bpl, Relative, 2, 0),                // $10
+    op(Ora, IndirectY, 5, PAGE),            // $11
+    op(Jam, Implied, 2, UNDOC),             // $12
+    op(Slo, IndirectY, 8, UNDOC),           // $13
+    op(Nop, ZeroPageX, 4, UNDOC),           // $14
+    op(Ora, ZeroPageX, 4, 0),               // $15
+    op(Asl, ZeroPageX, 6, 0),               // $16
+    op(Slo, ZeroPageX, 6, UNDOC),           // $17
+    op(Clc, Implied, 2, 0),                 // $18
+    op(Ora, AbsoluteY, 4, PAGE),            // $19
+    op(Nop, Implied, 2, UNDOC),             // $1A
+    op(Slo, AbsoluteY, 7, UNDOC),           // $1B
+    op(Nop, AbsoluteX, 4, PAGE | UNDOC),    // $1C
+    op(Ora, AbsoluteX, 4, PAGE),            // $1D
+    op(Asl, AbsoluteX, 7, 0),               // $1E
+    op(Slo, AbsoluteX, 7, UNDOC),           // $1F
+    op(Jsr, Absolute, 6, 0),                // $20
+    op(And, IndirectX, 6, 0),               // $21
+    op(Jam, Implied, 2, UNDOC),             // $22
+    op(Rla, IndirectX, 8, UNDOC),           // $23
+    op(Bit, ZeroPage, 3, 0),                // $24
+    op(And, ZeroPage, 3, 0),                // $25
+    op(Rol, ZeroPage, 5, 0),                // $26
+    op(Rla, ZeroPage, 5, UNDOC),            // $27
+    op(Plp, Implied, 4, 0),                 // $28
+    op(And, Immediate, 2, 0),               // $29
+    op(Rol, Accumulator, 2, 0),             // $2A
+    op(Anc, Immediate, 2, UNDOC),           // $2B
+    op(Bit, Absolute, 4, 0),                // $2C
+    op(And, Absolute, 4, 0),                // $2D
+    op(Rol, Absolute, 6, 0),                // $2E
+    op(Rla, Absolute, 6, UNDOC),            // $2F
+    op(Bmi, Relative, 2, 0),                // $30
+    op(And, IndirectY, 5, PAGE),            // $31
+    op(Jam, Implied, 2, UNDOC),             // $32
+    op(Rla, IndirectY, 8, UNDOC),           // $33
+    op(Nop, ZeroPageX, 4, UNDOC),           // $34
+    op(And, ZeroPageX, 4, 0),               // $35
+    op(Rol, ZeroPageX, 6, 0),               // $36
+    op(Rla, ZeroPageX, 6, UNDOC),           // $37
+    op(Sec, Implied, 2, 0),                 // $38
+    op(And, AbsoluteY, 4, PAGE),            // $39
+    op(Nop, Implied, 2, UNDOC),             // $3A
+    op(Rla, AbsoluteY, 7, UNDOC),           // $3B
+    op(Nop, AbsoluteX, 4, PAGE | UNDOC),    // $3C
+    op(And, AbsoluteX, 4, PAGE),            // $3D
+    op(Rol, AbsoluteX, 7, 0),               // $3E
+    op(Rla, AbsoluteX, 7, UNDOC),           // $3F
+    op(Rti, Implied, 6, 0),                 // $40
+    op(Eor, IndirectX, 6, 0),               // $41
+    op(Jam, Implied, 2, UNDOC),             // $42
+    op(Sre, IndirectX, 8, UNDOC),           // $43
+    op(Nop, ZeroPage, 3, UNDOC),            // $44
+    op(Eor, ZeroPage, 3, 0),                // $45
+    op(Lsr, ZeroPage, 5, 0),                // $46
+    op(Sre, ZeroPage, 5, UNDOC),            // $47
+    op(Pha, Implied, 3, 0),                 // $48
+    op(Eor, Immediate, 2, 0),               // $49
+    op(Lsr, Accumulator, 2, 0),             // $4A
+    op(Alr, Immediate, 2, UNDOC),           // $4B
+    op(Jmp, Absolute, 3, 0),                // $4C
+    op(Eor, Absolute, 4, 0),                // $4D
+    op(Lsr, Absolute, 6, 0),                // $4E
+    op(Sre, Absolute, 6, UNDOC),            // $4F
+    op(Bvc, Relative, 2, 0),                // $50
+    op(Eor, IndirectY, 5, PAGE),            // $51
+    op(Jam, Implied, 2, UNDOC),             // $52
+    op(Sre, IndirectY, 8, UNDOC),           // $53
+    op(Nop, ZeroPageX, 4, UNDOC),           // $54
+    op(Eor, ZeroPageX, 4, 0),               // $55
+    op(Lsr, ZeroPageX, 6, 0),               // $56
+    op(Sre, ZeroPageX, 6, UNDOC),           // $57
+    op(Cli, Implied, 2, 0),                 // $58
+    op(Eor, AbsoluteY, 4, PAGE),            // $59
+    op(Nop, Implied, 2, UNDOC),             // $5A
+    op(Sre, AbsoluteY, 7, UNDOC),           // $5B
+    op(Nop, AbsoluteX, 4, PAGE | UNDOC),    // $5C
+    op(Eor, AbsoluteX, 4, PAGE),            // $5D
+    op(Lsr, AbsoluteX, 7, 0),               // $5E
+    op(Sre, AbsoluteX, 7, UNDOC),           // $5F
+    op(Rts, Implied, 6, 0),                 // $60
+    op(Adc, IndirectX, 6, 0),               // $61
+    op(Jam, Implied, 2, UNDOC),             // $62
+    op(Rra, IndirectX, 8, UNDOC),           // $63
+    op(Nop, ZeroPage, 3, UNDOC),            // $64
+    op(Adc, ZeroPage, 3, 0),                // $65
+    op(Ror, ZeroPage, 5, 0),                // $66
+    op(Rra, ZeroPage, 5, UNDOC),            // $67
+    op(Pla, Implied, 4, 0),                 // $68
+    op(Adc, Immediate, 2, 0),               // $69
+    op(Ror, Accumulator, 2, 0),             // $6A
+    op(Arr, Immediate, 2, UNDOC),           // $6B
+    op(Jmp, Indirect, 5, 0),                // $6C
+    op(Adc, Absolute, 4, 0),                // $6D
+    op(Ror, Absolute, 6, 0),                // $6E
+    op(Rra, Absolute, 6, UNDOC),            // $6F
+    op(Bvs, Relative, 2, 0),                // $70
+    op(Adc, IndirectY, 5, PAGE),            // $71
+    op(Jam, Implied, 2, UNDOC),             // $72
+    op(Rra, IndirectY, 8, UNDOC),           // $73
+    op(Nop, ZeroPageX, 4, UNDOC),           // $74
+    op(Adc, ZeroPageX, 4, 0),               // $75
+    op(Ror, ZeroPageX, 6, 0),               // $76
+    op(Rra, ZeroPageX, 6, UNDOC),           // $77
+    op(Sei, Implied, 2, 0),                 // $78
+    op(Adc, AbsoluteY, 4, PAGE),            // $79
+    op(Nop, Implied, 2, UNDOC),             // $7A
+    op(Rra, AbsoluteY, 7, UNDOC),           // $7B
+    op(Nop, AbsoluteX, 4, PAGE | UNDOC),    // $7C
+    op(Adc, AbsoluteX, 4, PAGE),            // $7D
+    op(Ror, AbsoluteX, 7, 0),               // $7E
+    op(Rra, AbsoluteX, 7, UNDOC),           // $7F
+    op(Nop, Immediate, 2, UNDOC),           // $80
+    op(Sta, IndirectX, 6, 0),               // $81
+    op(Nop, Immediate, 2, UNDOC),           // $82
+    op(Sax, IndirectX, 6, UNDOC),           // $83
+    op(Sty, ZeroPage, 3, 0),                // $84
+    op(Sta, ZeroPage, 3, 0),                // $85
+    op(Stx, ZeroPage, 3, 0),                // $86
+    op(Sax, ZeroPage, 3, UNDOC),            // $87
+    op(Dey, Implied, 2, 0),                 // $88
+    op(Nop, Immediate, 2, UNDOC),           // $89
+    op(Txa, Implied, 2, 0),                 // $8A
+    op(Ane, Immediate, 2, UNDOC),           // $8B
+    op(Sty, Absolute, 4, 0),                // $8C
+    op(Sta, Absolute, 4, 0),                // $8D
+    op(Stx, Absolute, 4, 0),                // $8E
+    op(Sax, Absolute, 4, UNDOC),            // $8F
+    op(Bcc, Relative, 2, 0),                // $90
+    op(Sta, IndirectY, 6, 0),               // $91
+    op(Jam, Implied, 2, UNDOC),             // $92
+    op(Sha, IndirectY, 6, UNDOC),           // $93
+    op(Sty, ZeroPageX, 4, 0),               // $94
+    op(Sta, ZeroPageX, 4, 0),               // $95
+    op(Stx, ZeroPageY, 4, 0),               // $96
+    op(Sax, ZeroPageY, 4, UNDOC),           // $97
+    op(Tya, Implied, 2, 0),                 // $98
+    op(Sta, AbsoluteY, 5, 0),               // $99
+    op(Txs, Implied, 2, 0),                 // $9A
+    op(Tas, AbsoluteY, 5, UNDOC),           // $9B
+    op(Shy, AbsoluteX, 5, UNDOC),           // $9C
+    op(Sta, AbsoluteX, 5, 0),               // $9D
+    op(Shx, AbsoluteY, 5, UNDOC),           // $9E
+    op(Sha, AbsoluteY, 5, UNDOC),           // $9F
+    op(Ldy, Immediate, 2, 0),               // $A0
+    op(Lda, IndirectX, 6, 0),               // $A1
+    op(Ldx, Immediate, 2, 0),               // $A2
+    op(Lax, IndirectX, 6, UNDOC),           // $A3
+    op(Ldy, ZeroPage, 3, 0),                // $A4
+    op(Lda, ZeroPage, 3, 0),                // $A5
+    op(Ldx, ZeroPage, 3, 0),                // $A6
+    op(Lax, ZeroPage, 3, UNDOC),            // $A7
+    op(Tay, Implied, 2, 0),                 // $A8
+    op(Lda, Immediate, 2, 0),               // $A9
+    op(Tax, Implied, 2, 0),                 // $AA
+    op(Lxa, Immediate, 2, UNDOC),           // $AB
+    op(Ldy, Absolute, 4, 0),                // $AC
+    op(Lda, Absolute, 4, 0),                // $AD
+    op(Ldx, Absolute, 4, 0),                // $AE
+    op(Lax, Absolute, 4, UNDOC),            // $AF
+    op(Bcs, Relative, 2, 0),                // $B0
+    op(Lda, IndirectY, 5, PAGE),            // $B1
+    op(Jam, Implied, 2, UNDOC),             // $B2
+    op(Lax, IndirectY, 5, PAGE | UNDOC),    // $B3
+    op(Ldy, ZeroPageX, 4, 0),               // $B4
+    op(Lda, ZeroPageX, 4, 0),               // $B5
+    op(Ldx, ZeroPageY, 4, 0),               // $B6
+    op(Lax, ZeroPageY, 4, UNDOC),           // $B7
+    op(Clv, Implied, 2, 0),                 // $B8
+    op(Lda, AbsoluteY, 4, PAGE),            // $B9
+    op(Tsx, Implied, 2, 0),                 // $BA
+    op(Las, AbsoluteY, 4, PAGE | UNDOC),    // $BB
+    op(Ldy, AbsoluteX, 4, PAGE),            // $BC
+    op(Lda, AbsoluteX, 4, PAGE),            // $BD
+    op(Ldx, AbsoluteY, 4, PAGE),            // $BE
+    op(Lax, AbsoluteY, 4, PAGE | UNDOC),    // $BF
+    op(Cpy, Immediate, 2, 0),               // $C0
+    op(Cmp, IndirectX, 6, 0),               // $C1
+    op(Nop, Immediate, 2, UNDOC),           // $C2
+    op(Dcp, IndirectX, 8, UNDOC),           // $C3
+    op(Cpy, ZeroPage, 3, 0),                // $C4
+    op(Cmp, ZeroPage, 3, 0),                // $C5
+    op(Dec, ZeroPage, 5, 0),                // $C6
+    op(Dcp, ZeroPage, 5, UNDOC),            // $C7
+    op(Iny, Implied, 2, 0),                 // $C8
+    op(Cmp, Immediate, 2, 0),               // $C9
+    op(Dex, Implied, 2, 0),                 // $CA
+    op(Sbx, Immediate, 2, UNDOC),           // $CB
+    op(Cpy, Absolute, 4, 0),                // $CC
+    op(Cmp, Absolute, 4, 0),                // $CD
+    op(Dec, Absolute, 6, 0),                // $CE
+    op(Dcp, Absolute, 6, UNDOC),            // $CF
+    op(Bne, Relative, 2, 0),                // $D0
+    op(Cmp, IndirectY, 5, PAGE),            // $D1
+    op(Jam, Implied, 2, UNDOC),             // $D2
+    op(Dcp, IndirectY, 8, UNDOC),           // $D3
+    op(Nop, ZeroPageX, 4, UNDOC),           // $D4
+    op(Cmp, ZeroPageX, 4, 0),               // $D5
+    op(Dec, ZeroPageX, 6, 0),               // $D6
+    op(Dcp, ZeroPageX, 6, UNDOC),           // $D7
+    op(Cld, Implied, 2, 0),                 // $D8
+    op(Cmp, AbsoluteY, 4, PAGE),            // $D9
+    op(Nop, Implied, 2, UNDOC),             // $DA
+    op(Dcp, AbsoluteY, 7, UNDOC),           // $DB
+    op(Nop, AbsoluteX, 4, PAGE | UNDOC),    // $DC
+    op(Cmp, AbsoluteX, 4, PAGE),            // $DD
+    op(Dec, AbsoluteX, 7, 0),               // $DE
+    op(Dcp, AbsoluteX, 7, UNDOC),           // $DF
+    op(Cpx, Immediate, 2, 0),               // $E0
+    op(Sbc, IndirectX, 6, 0),               // $E1
+    op(Nop, Immediate, 2, UNDOC),           // $E2
+    op(Isc, IndirectX, 8, UNDOC),           // $E3
+    op(Cpx, ZeroPage, 3, 0),                // $E4
+    op(Sbc, ZeroPage, 3, 0),                // $E5
+    op(Inc, ZeroPage, 5, 0),                // $E6
+    op(Isc, ZeroPage, 5, UNDOC),            // $E7
+    op(Inx, Implied, 2, 0),                 // $E8
+    op(Sbc, Immediate, 2, 0),               // $E9
+    op(Nop, Implied, 2, 0),                 // $EA
+    op(Sbc, Immediate, 2, UNDOC),           // $EB
+    op(Cpx, Absolute, 4, 0),                // $EC
+    op(Sbc, Absolute, 4, 0),                // $ED
+    op(Inc, Absolute, 6, 0),                // $EE
+    op(Isc, Absolute, 6, UNDOC),            // $EF
+    op(Beq, Relative, 2, 0),                // $F0
+    op(Sbc, IndirectY, 5, PAGE),            // $F1
+    op(Jam, Implied, 2, UNDOC),             // $F2
+    op(Isc, IndirectY, 8, UNDOC),           // $F3
+    op(Nop, ZeroPageX, 4, UNDOC),           // $F4
+    op(Sbc, ZeroPageX, 4, 0),               // $F5
+    op(Inc, ZeroPageX, 6, 0),               // $F6
+    op(Isc, ZeroPageX, 6, UNDOC),           // $F7
+    op(Sed, Implied, 2, 0),                 // $F8
+    op(Sbc, AbsoluteY, 4, PAGE),            // $F9
+    op(Nop, Implied, 2, UNDOC),             // $FA
+    op(Isc, AbsoluteY, 7, UNDOC),           // $FB
+    op(Nop, AbsoluteX, 4, PAGE | UNDOC),    // $FC
+    op(Sbc, AbsoluteX, 4, PAGE),            // $FD
+    op(Inc, AbsoluteX, 7, 0),               // $FE
+    op(Isc, AbsoluteX, 7, UNDOC),           // $FF
+];
+
+/// Find the opcode byte for a mnemonic and addressing mode.
+/// Documented encodings are preferred over undocumented duplicates.
+pub fn encode(mnemonic: Mnemonic, mode: AddressMode) -> Option<u8> {
+    let mut fallback = None;
+    for (byte, op) in OPCODES.iter().enumerate() {
+        if op.mnemonic == mnemonic && op.mode == mode {
+            if !op.undocumented {
+                return Some(byte as u8);
+            }
+            fallback = fallback.or(Some(byte as u8));
+        }
+    }
+    fallback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lengths() {
+        assert_eq!(OPCODES[0xA9].length(), 2); // LDA #imm
+        assert_eq!(OPCODES[0xBD].length(), 3); // LDA abs,X
+        assert_eq!(OPCODES[0x0A].length(), 1); // ASL A
+        assert_eq!(OPCODES[0x6C].length(), 3); // JMP (abs)
+        assert_eq!(OPCODES[0xD0].length(), 2); // BNE
+    }
+
+    #[test]
+    fn test_encode_prefers_documented() {
+        assert_eq!(encode(Sbc, Immediate), Some(0xE9));
+        assert_eq!(encode(Nop, Implied), Some(0xEA));
+        assert_eq!(encode(Lax, ZeroPage), Some(0xA7));
+        assert_eq!(encode(Sta, Immediate), None);
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        for byte in 0..=255u8 {
+            let op = OPCODES[byte as usize];
+            let encoded = encode(op.mnemonic, op.mode).unwrap();
+            assert_eq!(OPCODES[encoded as usize].mnemonic, op.mnemonic);
+            assert_eq!(OPCODES[encoded as usize].mode, op.mode);
+        }
+    }
+}
@@ -1,121 +1,457 @@
-/// Plus4 Emulator Core
-/// Copyright (C) 2009 Florian Wolff (florian@donuz.de)
-/// Rust port 2025
-///
-/// This program is free software; you can redistribute it and/or
-/// modify it under the terms of the GNU General Public License
-/// as published by the Free Software Foundation; either version 2
-/// of the License, or (at your option) any later version.
-
+//! Plus4 Emulator Core
+//! Copyright (C) 2009 Florian Wolff (florian@donuz.de)
+//! Rust port 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+use crate::cartridge::{Cartridge, CartridgeSlot};
 use crate::cpu_state::CpuState;
+use crate::disassembler::{self, DisassembledLine};
+use crate::expansion::RamExpansion;
+use crate::machine::MachineModel;
+use crate::opcodes::{AddressMode, Mnemonic, OPCODES};
+use crate::roms::RomSet;
+use crate::sound::{TedSound, SAMPLE_RATE};
+use crate::symbols::SymbolTable;
+use crate::timers::TedTimers;
+use crate::trace::{TraceEntry, Tracer};
+use crate::wav::WavWriter;
 
 // Constants
-pub const CLOCK_FREQUENCY: u32 = 885000;
-// pub const IRQ_FREQUENCY: u32 = CLOCK_FREQUENCY / 60;
+// PAL double clock (17.734475 MHz / 10), which also drives the sound generator
+pub const DOUBLE_CLOCK_FREQUENCY: u32 = 1_773_447;
+#[deprecated(note = "PAL only; use MachineModel::clock_frequency()")]
+pub const CLOCK_FREQUENCY: u32 = DOUBLE_CLOCK_FREQUENCY / 2;
 pub const RASTER_LINES: u32 = 312;
 // pub const SCREEN_REFRESH_FREQUENCY: u32 = 57;
+// A raster line lasts 114 double-clock cycles. The CPU uses one of them per
+// cycle in double-clock mode and two in single-clock mode.
 pub const TICKS_PER_RASTER_LINE: u32 = 114;
-pub const TICKS_PER_BLINK_INTERVAL: u32 = CLOCK_FREQUENCY / 8;
+// IRR/IMR bits of the raster (1), timer A (3), timer B (4) and timer C (6)
+// interrupt sources
+const IRQ_SOURCES: u8 = 0x5A;
+// CPU cycles to push PC and flags and fetch the IRQ vector
+const IRQ_ENTRY_CYCLES: u32 = 7;
+// Double-clock cycles of a line in which TED fetches the characters and
+// bitmap data of the 320 display pixels. On display lines the CPU runs
+// single-clock here, and on badlines it is halted for the attribute fetch.
+const FETCH_WINDOW_START: u32 = 16;
+const FETCH_WINDOW_END: u32 = FETCH_WINDOW_START + 80;
+// Double-clock cycle of a line at which the beam enters the visible frame
+// (4 pixels per cycle)
+const FRAME_FIRST_CYCLE: u32 = FETCH_WINDOW_START - (BORDER_LEFT / 4) as u32;
 pub const SCREEN_WIDTH: usize = 320;
 pub const SCREEN_HEIGHT: usize = 200;
 pub const FIRST_SCREEN_LINE: usize = 3;
 
+// Visible PAL frame including the border around the 320x200 display window.
+// NTSC machines have fewer raster lines and only fill the top
+// MachineModel::frame_height() lines.
+pub const FRAME_WIDTH: usize = 384;
+pub const FRAME_HEIGHT: usize = 288;
+pub const BORDER_LEFT: usize = (FRAME_WIDTH - SCREEN_WIDTH) / 2;
+pub const BORDER_TOP: usize = (FRAME_HEIGHT - SCREEN_HEIGHT) / 2;
+
+// ROM slots selectable through the bank latch at 0xFDD0-0xFDDF
+pub const ROM_SLOTS: usize = 4;
+pub const ROM_SLOT_SYSTEM: usize = 0;
+pub const ROM_SLOT_FUNCTION: usize = 1;
+pub const ROM_SLOT_CARTRIDGE1: usize = 2;
+pub const ROM_SLOT_CARTRIDGE2: usize = 3;
+pub const ROM_BANK_SIZE: usize = 0x4000;
+
 pub struct Plus4 {
     // Memory
     ram: [u8; 0x10000],
-    rom: Vec<u8>,
-    rom3plus1: Vec<u8>,
-
-    // ROM configuration
+    // 16K ROM slots at 0x8000-0xBFFF (low) and 0xC000-0xFFFF (high); empty
+    // slots are not populated
+    rom_low: [Vec<u8>; ROM_SLOTS],
+    rom_high: [Vec<u8>; ROM_SLOTS],
+
+    // ROM configuration: 0xFF3E/0xFF3F select ROM or RAM, the bank latch
+    // written through 0xFDD0-0xFDDF selects the low (bits 0-1) and high
+    // (bits 2-3) slots
     rom_active: bool,
     rom_config: u8,
 
+    // Flat 64K RAM without ROM banking or I/O side effects (CPU test suites)
+    bare_cpu: bool,
+
+    // Machine configuration: RAM addresses are masked to the installed
+    // size, so 16K machines see their RAM mirrored
+    model: MachineModel,
+    ram_mask: usize,
+    raster_lines: u32,
+
+    // Optional RAM expansion: `ram` is bank 0, the other 64K banks follow
+    // each other in `expansion_ram`
+    expansion: RamExpansion,
+    expansion_ram: Vec<u8>,
+    bank_register: u8,
+
     // CPU
     pub cpu: CpuState,
 
     // Timing
     pub clock_ticks: u32,
+    cycle_count: u64,
+    // Double-clock cycles since power-on, and within the current raster line
+    double_cycles: u64,
     clock_counter: u32,
-    flash_counter: u32,
+    // Cycles of the current instruction before its write cycle
+    write_delay: u32,
+    // Pixels of the current frame line already drawn
+    line_x: usize,
     raster_line: u32,
-    flash_on: bool,
+    // 4-bit flash counter (FF1F bits 3-6), advanced once per frame
+    frame_counter: u8,
+    // Flashing characters and the cursor are shown while set; toggles
+    // each time the flash counter wraps, so every 16 frames
+    flash_phase: bool,
+    // Set when the raster wraps to the top of the next frame; unlike the
+    // flash counter it cannot be written by the CPU
+    frame_done: bool,
+
+    // Set by the indexed addressing helpers when the effective address
+    // crosses a page boundary
+    page_crossed: bool,
 
     // Timers
-    timer_on: [bool; 3],
-    timer_overflow: [bool; 3],
+    timers: TedTimers,
 
-    // Screen buffer
-    pub pixels: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
+    // Frame buffer: the display window starts at (BORDER_LEFT, BORDER_TOP)
+    pub pixels: [[u8; FRAME_WIDTH]; FRAME_HEIGHT],
 
     // Keyboard matrix state
     keyboard_matrix: [[bool; 8]; 8],
+
+    // Sound generator, sampled in step with the emulated cycles
+    sound: TedSound,
+    // WAV file receiving the sound output, written once per frame
+    recorder: Option<WavWriter<std::io::BufWriter<std::fs::File>>>,
+    // First failed write to the recording, returned by stop_recording()
+    recording_error: Option<std::io::Error>,
+
+    // Labels for disassembly
+    pub symbols: SymbolTable,
+
+    // Optional instruction trace logger
+    pub tracer: Option<Tracer>,
+}
+
+impl Default for Plus4 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Plus4 {
     pub fn new() -> Self {
+        Self::with_model(MachineModel::Plus4Pal)
+    }
+
+    pub fn with_model(model: MachineModel) -> Self {
         Self {
             ram: [0; 0x10000],
-            rom: vec![0; 0x8000],
-            rom3plus1: vec![0; 0x8000],
+            rom_low: Default::default(),
+            rom_high: Default::default(),
             rom_active: true,
             rom_config: 0,
+            bare_cpu: false,
+            model,
+            ram_mask: model.ram_size() - 1,
+            raster_lines: model.raster_lines(),
+            expansion: RamExpansion::None,
+            expansion_ram: Vec::new(),
+            bank_register: 0,
             cpu: CpuState::new(),
             clock_ticks: 0,
+            cycle_count: 0,
+            double_cycles: 0,
+            write_delay: 0,
+            line_x: 0,
             clock_counter: 0,
-            flash_counter: 0,
             raster_line: 0,
-            flash_on: false,
-            timer_on: [false; 3],
-            timer_overflow: [false; 3],
-            pixels: [[0; SCREEN_WIDTH]; SCREEN_HEIGHT],
+            frame_counter: 0,
+            flash_phase: false,
+            frame_done: false,
+            page_crossed: false,
+            timers: TedTimers::new(),
+            pixels: [[0; FRAME_WIDTH]; FRAME_HEIGHT],
             keyboard_matrix: [[false; 8]; 8],
+            sound: TedSound::new(model.double_clock_frequency(), SAMPLE_RATE),
+            recorder: None,
+            recording_error: None,
+            symbols: SymbolTable::plus4_rom(),
+            tracer: None,
+        }
+    }
+
+    pub fn model(&self) -> MachineModel {
+        self.model
+    }
+
+    // Frames per second of the emulated video standard
+    pub fn frame_rate(&self) -> f32 {
+        self.model.frame_rate()
+    }
+
+    // Lines of `pixels` drawn for the emulated video standard
+    pub fn frame_height(&self) -> usize {
+        self.model.frame_height()
+    }
+
+    // Bare 6502 with 64K of flat RAM: no ROM banking, no TED or I/O registers.
+    // Used to run CPU test suites such as Klaus Dormann's functional test.
+    pub fn new_bare_cpu() -> Self {
+        let mut plus4 = Self::new();
+        plus4.bare_cpu = true;
+        plus4
+    }
+
+    // Copy data straight into RAM, bypassing I/O side effects
+    pub fn load_ram(&mut self, start: u16, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            self.ram[(start as usize + i) & 0xFFFF] = byte;
         }
     }
 
+    // System ROM (BASIC low, KERNAL high) and 3-plus-1 function ROM, as
+    // 32K images
     pub fn load_rom(&mut self, rom_data: &[u8], rom3plus1_data: &[u8]) {
-        self.rom = rom_data.to_vec();
-        self.rom3plus1 = rom3plus1_data.to_vec();
+        self.load_rom_slot(ROM_SLOT_SYSTEM, rom_data);
+        if self.model.has_function_rom() {
+            self.load_rom_slot(ROM_SLOT_FUNCTION, rom3plus1_data);
+        }
+    }
+
+    // BASIC, KERNAL and the function ROM, if the model has one
+    pub fn load_rom_set(&mut self, roms: &RomSet) {
+        self.load_rom_low(ROM_SLOT_SYSTEM, &roms.basic);
+        self.load_rom_high(ROM_SLOT_SYSTEM, &roms.kernal);
+        if self.model.has_function_rom() {
+            self.load_rom_low(ROM_SLOT_FUNCTION, &roms.function_low);
+            self.load_rom_high(ROM_SLOT_FUNCTION, &roms.function_high);
+        }
+    }
+
+    // Fill a ROM slot from an image: the first 16K go to the low half, the
+    // next 16K (if present) to the high half. A shorter image is padded
+    // with 0xFF, as unused ROM space usually is.
+    pub fn load_rom_slot(&mut self, slot: usize, data: &[u8]) {
+        let (low, high) = data.split_at(data.len().min(ROM_BANK_SIZE));
+        self.rom_low[slot] = Self::rom_bank(low);
+        self.rom_high[slot] = Self::rom_bank(&high[..high.len().min(ROM_BANK_SIZE)]);
+    }
+
+    pub fn load_rom_low(&mut self, slot: usize, data: &[u8]) {
+        self.rom_low[slot] = Self::rom_bank(data);
+    }
+
+    pub fn load_rom_high(&mut self, slot: usize, data: &[u8]) {
+        self.rom_high[slot] = Self::rom_bank(data);
+    }
+
+    // Fill a ROM slot from a 16K or 32K image file
+    pub fn load_rom_file<P: AsRef<std::path::Path>>(&mut self, slot: usize, path: P) -> std::io::Result<()> {
+        let data = std::fs::read(path)?;
+        if data.len() != ROM_BANK_SIZE && data.len() != 2 * ROM_BANK_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("ROM image must be 16K or 32K, got {} bytes", data.len()),
+            ));
+        }
+        self.load_rom_slot(slot, &data);
+        Ok(())
+    }
+
+    // Plug a cartridge into C1 or C2. Autostart cartridges are started by
+    // the KERNAL at the next reset.
+    pub fn insert_cartridge(&mut self, slot: CartridgeSlot, cartridge: &Cartridge) {
+        let slot = Self::cartridge_rom_slot(slot);
+        self.load_rom_low(slot, &cartridge.low);
+        self.load_rom_high(slot, &cartridge.high);
+    }
+
+    pub fn remove_cartridge(&mut self, slot: CartridgeSlot) {
+        let slot = Self::cartridge_rom_slot(slot);
+        self.rom_low[slot].clear();
+        self.rom_high[slot].clear();
+    }
+
+    fn cartridge_rom_slot(slot: CartridgeSlot) -> usize {
+        match slot {
+            CartridgeSlot::C1 => ROM_SLOT_CARTRIDGE1,
+            CartridgeSlot::C2 => ROM_SLOT_CARTRIDGE2,
+        }
+    }
+
+    fn rom_bank(data: &[u8]) -> Vec<u8> {
+        if data.is_empty() {
+            return Vec::new();
+        }
+        let mut bank = data[..data.len().min(ROM_BANK_SIZE)].to_vec();
+        bank.resize(ROM_BANK_SIZE, 0xFF);
+        bank
+    }
+
+    // ROM byte at 0x8000-0xFFFF for the current bank latch. The KERNAL page
+    // 0xFC00-0xFCFF stays visible whichever high slot is selected, so bank
+    // switching code there keeps running.
+    fn read_rom(&self, addr: usize) -> u8 {
+        let bank = if addr < 0xC000 {
+            &self.rom_low[(self.rom_config & 3) as usize]
+        } else if (0xFC00..0xFD00).contains(&addr) {
+            &self.rom_high[ROM_SLOT_SYSTEM]
+        } else {
+            &self.rom_high[((self.rom_config >> 2) & 3) as usize]
+        };
+        // Empty sockets float high
+        bank.get(addr & (ROM_BANK_SIZE - 1)).copied().unwrap_or(0xFF)
     }
 
     // Memory access
     pub fn peek(&self, addr: u16) -> u8 {
         let addr = addr as usize;
 
+        if self.bare_cpu {
+            return self.ram[addr];
+        }
+
+        // TED counters are read live
+        if addr >= 0xFF00 {
+            if let Some(value) = self.read_ted_counter(addr) {
+                return value;
+            }
+        }
+
         // I/O area
-        if (addr >= 0xFD00 && addr <= 0xFDFF) || (addr >= 0xFF00 && addr <= 0xFF3F) {
+        if Self::is_io(addr) {
             return self.ram[addr];
         }
 
         // RAM area or ROM disabled
         if addr < 0x8000 || !self.rom_active {
-            return self.ram[addr];
+            return self.read_ram(addr & self.ram_mask);
         }
 
-        // ROM banking for 0x8000-0xBFFF
-        if addr < 0xC000 {
-            match self.rom_config & 3 {
-                0 => return self.rom[addr & 0x7FFF],
-                1 => return self.rom3plus1[addr & 0x7FFF],
-                _ => return 0,
-            }
+        self.read_rom(addr)
+    }
+
+    // Registers at 0xFD00-0xFDFF and 0xFF00-0xFF3F, kept at their own
+    // address in `ram` rather than in the (possibly mirrored) RAM
+    fn is_io(addr: usize) -> bool {
+        (0xFD00..=0xFDFF).contains(&addr) || (0xFF00..=0xFF3F).contains(&addr)
+    }
+
+    // RAM byte as the CPU sees it, through the expansion banks
+    fn read_ram(&self, addr: usize) -> u8 {
+        match self.expansion.cpu_bank(self.bank_register, addr) {
+            0 => self.ram[addr],
+            bank => self.expansion_ram[((bank - 1) << 16) | addr],
+        }
+    }
+
+    fn write_ram(&mut self, addr: usize, value: u8) {
+        match self.expansion.cpu_bank(self.bank_register, addr) {
+            0 => self.ram[addr] = value,
+            bank => self.expansion_ram[((bank - 1) << 16) | addr] = value,
         }
+    }
 
-        // System ROM area 0xFC00-0xFCFF
-        if addr >= 0xFC00 && addr < 0xFD00 {
-            return self.rom[addr & 0x7FFF];
+    // RAM byte fetched by TED for the display
+    fn ted_ram(&self, addr: usize) -> u8 {
+        let addr = addr & self.ram_mask;
+        match self.expansion.ted_bank(self.bank_register, addr) {
+            0 => self.ram[addr],
+            bank => self.expansion_ram[((bank - 1) << 16) | addr],
         }
+    }
+
+    pub fn ram_expansion(&self) -> RamExpansion {
+        self.expansion
+    }
+
+    // Fit a RAM expansion. The expansion banks start out cleared and bank 0
+    // is selected.
+    pub fn set_ram_expansion(&mut self, expansion: RamExpansion) {
+        self.expansion = expansion;
+        self.expansion_ram = vec![0; (expansion.banks() - 1) << 16];
+        self.bank_register = 0;
+    }
 
-        // ROM banking for 0xC000+
-        match (self.rom_config >> 2) & 3 {
-            0 => self.rom[addr & 0x7FFF],
-            1 => self.rom3plus1[addr & 0x7FFF],
-            _ => 0,
+    // Current value of the TED timers 0xFF00-0xFF05 and counters 0xFF1A-0xFF1F
+    fn read_ted_counter(&self, addr: usize) -> Option<u8> {
+        match addr {
+            // Timer counters
+            0xFF00..=0xFF05 => Some(self.timers.read(addr as u16)),
+            // Character position, bits 8-9 (unused bits read as 1)
+            0xFF1A => Some(0xFC | (self.character_position() >> 8) as u8),
+            // Character position, bits 0-7
+            0xFF1B => Some(self.character_position() as u8),
+            // Raster line, bit 8 (unused bits read as 1)
+            0xFF1C => Some(0xFE | (self.raster_line >> 8) as u8),
+            // Raster line, bits 0-7
+            0xFF1D => Some(self.raster_line as u8),
+            // Horizontal position in units of two pixels (0-227)
+            0xFF1E => Some((self.clock_counter.min(TICKS_PER_RASTER_LINE - 1) * 2) as u8),
+            // Bit 7 unused, bits 3-6 flash counter, bits 0-2 pixel row within the character
+            0xFF1F => Some(0x80 | ((self.frame_counter & 0x0F) << 3) | (self.display_line() & 7) as u8),
+            _ => None,
         }
     }
 
+    // Current raster line relative to the first badline (YSCROLL)
+    fn display_line(&self) -> u32 {
+        let yscroll = (self.ram[0xFF06] & 7) as u32;
+        self.raster_line.wrapping_sub(yscroll).min(SCREEN_HEIGHT as u32)
+    }
+
+    // Offset of the current character row in the video matrix
+    fn character_position(&self) -> u32 {
+        (self.display_line() / 8).min(25) * 40
+    }
+
+    // Current raster line, below the model's raster_lines() unless the
+    // program wrote a larger value to 0xFF1C/0xFF1D
+    pub fn raster_line(&self) -> u32 {
+        self.raster_line
+    }
+
+    // Disassemble `count` instructions starting at `addr`, as the CPU currently sees memory
+    pub fn disassemble(&self, addr: u16, count: usize) -> Vec<DisassembledLine> {
+        disassembler::disassemble(|a| self.peek(a), addr, count, &self.symbols)
+    }
+
+    // Load additional labels for disassembly from a symbol file
+    pub fn load_symbols<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<usize> {
+        self.symbols.load_from_file(path)
+    }
+
+    // True if a CPU read from `addr` currently hits ROM rather than RAM or I/O
+    pub fn rom_visible(&self, addr: u16) -> bool {
+        self.rom_active
+            && addr >= 0x8000
+            && !(0xFD00..=0xFDFF).contains(&addr)
+            && !(0xFF00..=0xFF3F).contains(&addr)
+    }
+
     pub fn poke(&mut self, addr: u16, value: u8) {
         let addr = addr as usize;
 
+        if self.bare_cpu {
+            self.ram[addr] = value;
+            return;
+        }
+
+        // Let the beam catch up before video registers change mid-line
+        if matches!(addr, 0xFF06..=0xFF07 | 0xFF0C..=0xFF0D | 0xFF12..=0xFF19) {
+            self.render_to_beam();
+        }
+
         match addr {
             0xFF3E => {
                 // Enable ROM
@@ -129,12 +465,23 @@ impl Plus4 {
                 // Bank switching
                 self.rom_config = (addr & 15) as u8;
             }
+            _ if self.expansion.register() == Some(addr) => {
+                // RAM expansion bank select
+                self.bank_register = value;
+            }
             _ => {}
         }
 
+        // Writes to IRR acknowledge interrupts instead of storing the value
+        let irr = self.ram[0xFF09];
+
         // Write value to RAM (including 0xFF08 for joystick port selection)
         if addr != 0xFF08 {
-            self.ram[addr] = value;
+            if Self::is_io(addr) {
+                self.ram[addr] = value;
+            } else {
+                self.write_ram(addr & self.ram_mask, value);
+            }
         }
 
         // println!("Poke: addr=0x{:04X}, value=0x{:02X}", addr, value);
@@ -146,14 +493,9 @@ impl Plus4 {
         }
 
         // TED chip registers
-        if addr >= 0xFF00 && addr <= 0xFF1F {
+        if (0xFF00..=0xFF1F).contains(&addr) {
             match addr {
-                0xFF00 => self.timer_on[0] = false,
-                0xFF01 => self.timer_on[0] = true,
-                0xFF02 => self.timer_on[1] = false,
-                0xFF03 => self.timer_on[1] = true,
-                0xFF04 => self.timer_on[2] = false,
-                0xFF05 => self.timer_on[2] = true,
+                0xFF00..=0xFF05 => self.timers.write(addr as u16, value),
                 0xFF08 => {
                     // Write to 0xFF08 triggers keyboard scan (after joystick handling)
                     // This is how the Plus/4 ROM scans the keyboard
@@ -167,13 +509,30 @@ impl Plus4 {
                     // self.p4_keyboard();
                 }
                 0xFF09 => {
-                    // IRR (Interrupt Request Register) - writing clears the interrupt bits
-                    // Only clear the bits that are set in the written value
-                    self.ram[0xFF09] &= !value;
+                    // IRR (Interrupt Request Register) - writing 1 bits acknowledges
+                    // the corresponding sources
+                    self.ram[0xFF09] = irr & !value;
+                    self.update_irq_line();
                 }
                 0xFF0A => {
-                    // IMR (Interrupt Mask Register) - controls which interrupts are enabled
-                    // Direct write to register (already handled by ram write above)
+                    // IMR (Interrupt Mask Register) - a newly enabled source that is
+                    // already latched asserts the IRQ line
+                    self.update_irq_line();
+                }
+                0xFF0E..=0xFF12 => {
+                    // Voice frequencies and sound control
+                    self.sound.write_register(addr as u16, value);
+                }
+                0xFF1C => {
+                    // Writing the raster counter moves the beam to another line
+                    self.raster_line = (self.raster_line & 0xFF) | ((value as u32 & 1) << 8);
+                }
+                0xFF1D => {
+                    self.raster_line = (self.raster_line & 0x100) | value as u32;
+                }
+                0xFF1F => {
+                    // Bits 3-6 preset the flash counter
+                    self.frame_counter = (value >> 3) & 0x0F;
                 }
                 _ => {}
             }
@@ -212,7 +571,6 @@ impl Plus4 {
         self.cpu.z = (flags & 2) != 0;
         self.cpu.i = (flags & 4) != 0;
         self.cpu.d = (flags & 8) != 0;
-        self.cpu.b = (flags & 16) != 0;
         self.cpu.v = (flags & 64) != 0;
         self.cpu.n = (flags & 128) != 0;
     }
@@ -223,7 +581,6 @@ impl Plus4 {
         if self.cpu.z { flags |= 2; }
         if self.cpu.i { flags |= 4; }
         if self.cpu.d { flags |= 8; }
-        if self.cpu.b { flags |= 16; }
         if self.cpu.v { flags |= 64; }
         if self.cpu.n { flags |= 128; }
         flags
@@ -231,9 +588,13 @@ impl Plus4 {
 
     // Reset
     pub fn hard_reset(&mut self) {
+        // Reset selects the system ROM in both halves
+        self.rom_config = 0;
+        self.rom_active = true;
+
         // Get reset vector from ROM
-        let reset_lo = self.rom[0xFFFC - 0x8000] as u16;
-        let reset_hi = self.rom[0xFFFD - 0x8000] as u16;
+        let reset_lo = self.read_rom(0xFFFC) as u16;
+        let reset_hi = self.read_rom(0xFFFD) as u16;
         self.cpu.pc = (reset_hi << 8) + reset_lo;
 
         self.cpu.sp = 0xFF;
@@ -244,19 +605,21 @@ impl Plus4 {
         self.cpu.i = true;
         self.cpu.n = false;
         self.cpu.c = false;
-        self.cpu.b = false;
         self.cpu.z = false;
         self.cpu.d = false;
         self.cpu.v = false;
+        self.cpu.jammed = false;
 
-        self.ram[0xFF04] = 4;
-        self.rom_config = 0;
-        self.rom_active = true;
+        self.timers.reset();
+        self.bank_register = 0;
 
         self.clock_counter = 0;
-        self.flash_on = false;
-        self.flash_counter = 0;
         self.raster_line = 0;
+        self.frame_counter = 0;
+        self.flash_phase = false;
+        self.frame_done = false;
+        self.line_x = 0;
+        self.sound.reset();
     }
 
     // Update keyboard matrix state from external keyboard
@@ -315,1236 +678,519 @@ impl Plus4 {
 
     // Helper: Get address based on addressing mode
     fn get_addr_zeropage(&self) -> u16 {
-        self.peek(self.cpu.pc + 1) as u16
+        self.peek(self.cpu.pc.wrapping_add(1)) as u16
     }
 
     fn get_addr_zeropage_x(&self) -> u16 {
-        self.peek(self.cpu.pc + 1).wrapping_add(self.cpu.xr) as u16
+        self.peek(self.cpu.pc.wrapping_add(1)).wrapping_add(self.cpu.xr) as u16
     }
 
     fn get_addr_zeropage_y(&self) -> u16 {
-        self.peek(self.cpu.pc + 1).wrapping_add(self.cpu.yr) as u16
+        self.peek(self.cpu.pc.wrapping_add(1)).wrapping_add(self.cpu.yr) as u16
     }
 
     fn get_addr_absolute(&self) -> u16 {
-        let lo = self.peek(self.cpu.pc + 1) as u16;
-        let hi = self.peek(self.cpu.pc + 2) as u16;
+        let lo = self.peek(self.cpu.pc.wrapping_add(1)) as u16;
+        let hi = self.peek(self.cpu.pc.wrapping_add(2)) as u16;
         (hi << 8) | lo
     }
 
-    fn get_addr_absolute_x(&self) -> u16 {
-        let lo = self.peek(self.cpu.pc + 1) as u16;
-        let hi = self.peek(self.cpu.pc + 2) as u16;
-        ((hi << 8) | lo).wrapping_add(self.cpu.xr as u16)
+    fn get_addr_absolute_x(&mut self) -> u16 {
+        let base = self.get_addr_absolute();
+        self.index_addr(base, self.cpu.xr)
     }
 
-    fn get_addr_absolute_y(&self) -> u16 {
-        let lo = self.peek(self.cpu.pc + 1) as u16;
-        let hi = self.peek(self.cpu.pc + 2) as u16;
-        ((hi << 8) | lo).wrapping_add(self.cpu.yr as u16)
+    fn get_addr_absolute_y(&mut self) -> u16 {
+        let base = self.get_addr_absolute();
+        self.index_addr(base, self.cpu.yr)
     }
 
     fn get_addr_indirect_x(&self) -> u16 {
-        let base = self.peek(self.cpu.pc + 1).wrapping_add(self.cpu.xr);
+        let base = self.peek(self.cpu.pc.wrapping_add(1)).wrapping_add(self.cpu.xr);
         let lo = self.peek(base as u16) as u16;
         let hi = self.peek(base.wrapping_add(1) as u16) as u16;
         (hi << 8) | lo
     }
 
-    fn get_addr_indirect_y(&self) -> u16 {
-        let base = self.peek(self.cpu.pc + 1);
+    fn get_addr_indirect_y(&mut self) -> u16 {
+        let base = self.peek(self.cpu.pc.wrapping_add(1));
         let lo = self.peek(base as u16) as u16;
         let hi = self.peek(base.wrapping_add(1) as u16) as u16;
-        ((hi << 8) | lo).wrapping_add(self.cpu.yr as u16)
+        self.index_addr((hi << 8) | lo, self.cpu.yr)
     }
 
-    // Execute one CPU instruction with full 6510 opcode table
-    pub fn execute_instruction(&mut self) {
-        let opcode = self.peek(self.cpu.pc);
-        self.clock_ticks = 2; // Default timing
-
-        // println!("Executing opcode 0x{:02X} at PC=0x{:04X}", opcode, self.cpu.pc);
-
-        match opcode {
-            // LDA - Load Accumulator
-            0xA9 => { // LDA #immediate
-                self.cpu.acc = self.peek(self.cpu.pc + 1);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0xA5 => { // LDA zeropage
-                let addr = self.get_addr_zeropage();
-                self.cpu.acc = self.peek(addr);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0xB5 => { // LDA zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                self.cpu.acc = self.peek(addr);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0xAD => { // LDA absolute
-                let addr = self.get_addr_absolute();
-                self.cpu.acc = self.peek(addr);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xBD => { // LDA absolute,X
-                let addr = self.get_addr_absolute_x();
-                self.cpu.acc = self.peek(addr);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xB9 => { // LDA absolute,Y
-                let addr = self.get_addr_absolute_y();
-                self.cpu.acc = self.peek(addr);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xA1 => { // LDA (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                self.cpu.acc = self.peek(addr);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0xB1 => { // LDA (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                self.cpu.acc = self.peek(addr);
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
+    // Add an index register to a base address and remember whether the
+    // result left the base page (read instructions take one extra cycle then)
+    fn index_addr(&mut self, base: u16, index: u8) -> u16 {
+        let addr = base.wrapping_add(index as u16);
+        self.page_crossed = (addr & 0xFF00) != (base & 0xFF00);
+        addr
+    }
 
-            // STA - Store Accumulator
-            0x85 => { // STA zeropage
-                let addr = self.get_addr_zeropage();
-                self.poke(addr, self.cpu.acc);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x95 => { // STA zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                self.poke(addr, self.cpu.acc);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0x8D => { // STA absolute
-                let addr = self.get_addr_absolute();
-                self.poke(addr, self.cpu.acc);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x9D => { // STA absolute,X
-                let addr = self.get_addr_absolute_x();
-                self.poke(addr, self.cpu.acc);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 5;
-            }
-            0x99 => { // STA absolute,Y
-                let addr = self.get_addr_absolute_y();
-                self.poke(addr, self.cpu.acc);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 5;
-            }
-            0x81 => { // STA (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                self.poke(addr, self.cpu.acc);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x91 => { // STA (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                self.poke(addr, self.cpu.acc);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
+    // Effective address of the current instruction's operand. Immediate and
+    // relative operands resolve to the operand byte itself, JMP (abs) to the
+    // pointer location.
+    fn get_operand_address(&mut self, mode: AddressMode) -> u16 {
+        match mode {
+            AddressMode::Implied | AddressMode::Accumulator => 0,
+            AddressMode::Immediate | AddressMode::Relative => self.cpu.pc.wrapping_add(1),
+            AddressMode::ZeroPage => self.get_addr_zeropage(),
+            AddressMode::ZeroPageX => self.get_addr_zeropage_x(),
+            AddressMode::ZeroPageY => self.get_addr_zeropage_y(),
+            AddressMode::Absolute | AddressMode::Indirect => self.get_addr_absolute(),
+            AddressMode::AbsoluteX => self.get_addr_absolute_x(),
+            AddressMode::AbsoluteY => self.get_addr_absolute_y(),
+            AddressMode::IndirectX => self.get_addr_indirect_x(),
+            AddressMode::IndirectY => self.get_addr_indirect_y(),
+        }
+    }
 
-            // LDX - Load X Register
-            0xA2 => { // LDX #immediate
-                self.cpu.xr = self.peek(self.cpu.pc + 1);
-                self.cpu.z = self.cpu.xr == 0;
-                self.cpu.n = (self.cpu.xr & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0xA6 => { // LDX zeropage
-                let addr = self.get_addr_zeropage();
-                self.cpu.xr = self.peek(addr);
-                self.cpu.z = self.cpu.xr == 0;
-                self.cpu.n = (self.cpu.xr & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0xB6 => { // LDX zeropage,Y
-                let addr = self.get_addr_zeropage_y();
-                self.cpu.xr = self.peek(addr);
-                self.cpu.z = self.cpu.xr == 0;
-                self.cpu.n = (self.cpu.xr & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0xAE => { // LDX absolute
-                let addr = self.get_addr_absolute();
-                self.cpu.xr = self.peek(addr);
-                self.cpu.z = self.cpu.xr == 0;
-                self.cpu.n = (self.cpu.xr & 0x80) != 0;
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xBE => { // LDX absolute,Y
-                let addr = self.get_addr_absolute_y();
-                self.cpu.xr = self.peek(addr);
-                self.cpu.z = self.cpu.xr == 0;
-                self.cpu.n = (self.cpu.xr & 0x80) != 0;
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
+    // Read-modify-write on the accumulator or memory, returns the new value
+    fn modify(&mut self, mode: AddressMode, addr: u16, op: fn(&mut CpuState, u8) -> u8) -> u8 {
+        if mode == AddressMode::Accumulator {
+            let value = self.cpu.acc;
+            self.cpu.acc = op(&mut self.cpu, value);
+            self.cpu.acc
+        } else {
+            let value = self.peek(addr);
+            let result = op(&mut self.cpu, value);
+            self.poke(addr, result);
+            result
+        }
+    }
 
-            // LDY - Load Y Register
-            0xA0 => { // LDY #immediate
-                self.cpu.yr = self.peek(self.cpu.pc + 1);
-                self.cpu.z = self.cpu.yr == 0;
-                self.cpu.n = (self.cpu.yr & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0xA4 => { // LDY zeropage
-                let addr = self.get_addr_zeropage();
-                self.cpu.yr = self.peek(addr);
-                self.cpu.z = self.cpu.yr == 0;
-                self.cpu.n = (self.cpu.yr & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0xB4 => { // LDY zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                self.cpu.yr = self.peek(addr);
-                self.cpu.z = self.cpu.yr == 0;
-                self.cpu.n = (self.cpu.yr & 0x80) != 0;
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0xAC => { // LDY absolute
-                let addr = self.get_addr_absolute();
-                self.cpu.yr = self.peek(addr);
-                self.cpu.z = self.cpu.yr == 0;
-                self.cpu.n = (self.cpu.yr & 0x80) != 0;
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xBC => { // LDY absolute,X
-                let addr = self.get_addr_absolute_x();
-                self.cpu.yr = self.peek(addr);
-                self.cpu.z = self.cpu.yr == 0;
-                self.cpu.n = (self.cpu.yr & 0x80) != 0;
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
+    // Relative branch: +1 cycle if taken, +1 more if the target is on another page
+    fn branch(&mut self, addr: u16, taken: bool) {
+        if taken {
+            let offset = self.peek(addr) as i8;
+            let target = self.cpu.pc.wrapping_add(offset as u16);
+            self.clock_ticks += if (target & 0xFF00) != (self.cpu.pc & 0xFF00) { 2 } else { 1 };
+            self.cpu.pc = target;
+        }
+    }
 
-            // STX - Store X Register
-            0x86 => { // STX zeropage
-                let addr = self.get_addr_zeropage();
-                self.poke(addr, self.cpu.xr);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x96 => { // STX zeropage,Y
-                let addr = self.get_addr_zeropage_y();
-                self.poke(addr, self.cpu.xr);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0x8E => { // STX absolute
-                let addr = self.get_addr_absolute();
-                self.poke(addr, self.cpu.xr);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
+    // Store helper for SHA/SHX/SHY/TAS: the value is ANDed with the high byte
+    // of the base address + 1, and on a page crossing that value also replaces
+    // the high byte of the effective address
+    fn store_high_and(&mut self, addr: u16, index: u8, value: u8) {
+        let base_addr = addr.wrapping_sub(index as u16);
+        let result = value & ((base_addr >> 8) as u8).wrapping_add(1);
+        let addr = if (addr & 0xFF00) != (base_addr & 0xFF00) {
+            ((result as u16) << 8) | (addr & 0xFF)
+        } else {
+            addr
+        };
+        self.poke(addr, result);
+    }
 
-            // STY - Store Y Register
-            0x84 => { // STY zeropage
-                let addr = self.get_addr_zeropage();
-                self.poke(addr, self.cpu.yr);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x94 => { // STY zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                self.poke(addr, self.cpu.yr);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0x8C => { // STY absolute
-                let addr = self.get_addr_absolute();
-                self.poke(addr, self.cpu.yr);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
+    // Execute one CPU instruction, decoded through the shared opcode table
+    pub fn execute_instruction(&mut self) {
+        let opcode = self.peek(self.cpu.pc);
+        let op = OPCODES[opcode as usize];
+
+        self.page_crossed = false;
+        let addr = self.get_operand_address(op.mode);
+        self.cpu.incr_pc(op.length());
+        self.clock_ticks = op.cycles as u32;
+        self.write_delay = self.clock_ticks - 1;
+
+        match op.mnemonic {
+            // Load/Store
+            Mnemonic::Lda => { let value = self.peek(addr); self.cpu.lda(value); }
+            Mnemonic::Ldx => { let value = self.peek(addr); self.cpu.ldx(value); }
+            Mnemonic::Ldy => { let value = self.peek(addr); self.cpu.ldy(value); }
+            Mnemonic::Sta => self.poke(addr, self.cpu.acc),
+            Mnemonic::Stx => self.poke(addr, self.cpu.xr),
+            Mnemonic::Sty => self.poke(addr, self.cpu.yr),
 
             // Transfer operations
-            0xAA => { self.cpu.tax(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // TAX
-            0x8A => { self.cpu.txa(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // TXA
-            0xA8 => { self.cpu.tay(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // TAY
-            0x98 => { self.cpu.tya(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // TYA
-            0xBA => { self.cpu.tsx(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // TSX
-            0x9A => { self.cpu.txs(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // TXS
+            Mnemonic::Tax => self.cpu.tax(),
+            Mnemonic::Txa => self.cpu.txa(),
+            Mnemonic::Tay => self.cpu.tay(),
+            Mnemonic::Tya => self.cpu.tya(),
+            Mnemonic::Tsx => self.cpu.tsx(),
+            Mnemonic::Txs => self.cpu.txs(),
 
             // Increment/Decrement
-            0xE8 => { self.cpu.inx(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // INX
-            0xCA => { self.cpu.dex(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // DEX
-            0xC8 => { self.cpu.iny(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // INY
-            0x88 => { self.cpu.dey(); self.cpu.incr_pc(1); self.clock_ticks = 2; } // DEY
+            Mnemonic::Inx => self.cpu.inx(),
+            Mnemonic::Dex => self.cpu.dex(),
+            Mnemonic::Iny => self.cpu.iny(),
+            Mnemonic::Dey => self.cpu.dey(),
+            Mnemonic::Inc => { self.modify(op.mode, addr, CpuState::do_inc); }
+            Mnemonic::Dec => { self.modify(op.mode, addr, CpuState::do_dec); }
 
             // Stack operations
-            0x48 => { // PHA
-                self.push(self.cpu.acc);
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 3;
+            Mnemonic::Pha => self.push(self.cpu.acc),
+            Mnemonic::Pla => { let value = self.pull(); self.cpu.lda(value); }
+            Mnemonic::Php => self.push(self.get_flags() | 0x10), // B is always set when pushed by PHP
+            Mnemonic::Plp => { let flags = self.pull(); self.set_flags(flags); }
+
+            // Logical and arithmetic operations
+            Mnemonic::And => { let value = self.peek(addr); self.cpu.do_and(value); }
+            Mnemonic::Ora => { let value = self.peek(addr); self.cpu.do_ora(value); }
+            Mnemonic::Eor => { let value = self.peek(addr); self.cpu.do_eor(value); }
+            Mnemonic::Adc => { let value = self.peek(addr); self.cpu.do_adc(value); }
+            Mnemonic::Sbc => { let value = self.peek(addr); self.cpu.do_sbc(value); }
+            Mnemonic::Bit => { let value = self.peek(addr); self.cpu.do_bit(value); }
+            Mnemonic::Cmp => { let value = self.peek(addr); self.cpu.do_cmp(self.cpu.acc, value); }
+            Mnemonic::Cpx => { let value = self.peek(addr); self.cpu.do_cmp(self.cpu.xr, value); }
+            Mnemonic::Cpy => { let value = self.peek(addr); self.cpu.do_cmp(self.cpu.yr, value); }
+
+            // Shift/Rotate operations
+            Mnemonic::Asl => { self.modify(op.mode, addr, CpuState::do_asl); }
+            Mnemonic::Lsr => { self.modify(op.mode, addr, CpuState::do_lsr); }
+            Mnemonic::Rol => { self.modify(op.mode, addr, CpuState::do_rol); }
+            Mnemonic::Ror => { self.modify(op.mode, addr, CpuState::do_ror); }
+
+            // Jumps and subroutines
+            Mnemonic::Jmp => {
+                if op.mode == AddressMode::Indirect {
+                    let lo = self.peek(addr) as u16;
+                    let hi = self.peek(addr.wrapping_add(1)) as u16;
+                    self.cpu.pc = (hi << 8) | lo;
+                } else {
+                    self.cpu.pc = addr;
+                }
             }
-            0x68 => { // PLA
-                let value = self.pull();
-                self.cpu.acc = value;
-                self.cpu.z = self.cpu.acc == 0;
-                self.cpu.n = (self.cpu.acc & 0x80) != 0;
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 4;
+            Mnemonic::Jsr => {
+                // The pushed return address points to the last byte of the JSR
+                self.push_word(self.cpu.pc.wrapping_sub(1));
+                self.cpu.pc = addr;
             }
-            0x08 => { // PHP
-                self.push(self.get_flags());
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 3;
+            Mnemonic::Rts => {
+                let addr = self.pull_word();
+                self.cpu.pc = addr.wrapping_add(1);
             }
-            0x28 => { // PLP
+            Mnemonic::Rti => {
                 let flags = self.pull();
                 self.set_flags(flags);
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 4;
+                self.cpu.pc = self.pull_word();
             }
 
-            // Logical operations
-            0x29 => { // AND #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0x25 => { // AND zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x35 => { // AND zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0x2D => { // AND absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x3D => { // AND absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x39 => { // AND absolute,Y
-                let addr = self.get_addr_absolute_y();
-                let value = self.peek(addr);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x21 => { // AND (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                let value = self.peek(addr);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x31 => { // AND (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                let value = self.peek(addr);
-                self.cpu.do_and(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
+            // Branch instructions
+            Mnemonic::Bcc => self.branch(addr, !self.cpu.c),
+            Mnemonic::Bcs => self.branch(addr, self.cpu.c),
+            Mnemonic::Beq => self.branch(addr, self.cpu.z),
+            Mnemonic::Bne => self.branch(addr, !self.cpu.z),
+            Mnemonic::Bmi => self.branch(addr, self.cpu.n),
+            Mnemonic::Bpl => self.branch(addr, !self.cpu.n),
+            Mnemonic::Bvc => self.branch(addr, !self.cpu.v),
+            Mnemonic::Bvs => self.branch(addr, self.cpu.v),
 
-            0x09 => { // ORA #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0x05 => { // ORA zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x15 => { // ORA zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0x0D => { // ORA absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x1D => { // ORA absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x19 => { // ORA absolute,Y
-                let addr = self.get_addr_absolute_y();
-                let value = self.peek(addr);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x01 => { // ORA (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                let value = self.peek(addr);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x11 => { // ORA (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                let value = self.peek(addr);
-                self.cpu.do_ora(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
+            // Flag operations
+            Mnemonic::Clc => self.cpu.c = false,
+            Mnemonic::Sec => self.cpu.c = true,
+            Mnemonic::Cli => self.cpu.i = false,
+            Mnemonic::Sei => self.cpu.i = true,
+            Mnemonic::Cld => self.cpu.d = false,
+            Mnemonic::Sed => self.cpu.d = true,
+            Mnemonic::Clv => self.cpu.v = false,
+
+            // NOP, including the undocumented multi-byte variants
+            Mnemonic::Nop => {}
+
+            Mnemonic::Brk => {
+                // BRK skips a padding byte, so the return address is opcode + 2.
+                // The pushed flags have B set and I as it was before the BRK.
+                self.push_word(self.cpu.pc.wrapping_add(1));
+                self.push(self.get_flags() | 0x10);
+                self.cpu.i = true;
+                let irq_lo = self.peek(0xFFFE) as u16;
+                let irq_hi = self.peek(0xFFFF) as u16;
+                self.cpu.pc = (irq_hi << 8) | irq_lo;
             }
 
-            0x49 => { // EOR #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0x45 => { // EOR zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x55 => { // EOR zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0x4D => { // EOR absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x5D => { // EOR absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x59 => { // EOR absolute,Y
-                let addr = self.get_addr_absolute_y();
-                let value = self.peek(addr);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x41 => { // EOR (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                let value = self.peek(addr);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
+            // Undocumented read-modify-write combinations
+            Mnemonic::Slo => { let result = self.modify(op.mode, addr, CpuState::do_asl); self.cpu.do_ora(result); }
+            Mnemonic::Rla => { let result = self.modify(op.mode, addr, CpuState::do_rol); self.cpu.do_and(result); }
+            Mnemonic::Sre => { let result = self.modify(op.mode, addr, CpuState::do_lsr); self.cpu.do_eor(result); }
+            Mnemonic::Rra => { let result = self.modify(op.mode, addr, CpuState::do_ror); self.cpu.do_adc(result); }
+            Mnemonic::Dcp => {
+                let result = self.peek(addr).wrapping_sub(1);
+                self.poke(addr, result);
+                self.cpu.do_cmp(self.cpu.acc, result);
             }
-            0x51 => { // EOR (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                let value = self.peek(addr);
-                self.cpu.do_eor(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
+            Mnemonic::Isc => {
+                let result = self.peek(addr).wrapping_add(1);
+                self.poke(addr, result);
+                self.cpu.do_sbc(result);
             }
 
-            // Compare operations
-            0xC9 => { // CMP #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0xC5 => { // CMP zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0xD5 => { // CMP zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
+            // Undocumented loads and stores
+            Mnemonic::Lax => { let value = self.peek(addr); self.cpu.do_lax(value); }
+            Mnemonic::Sax => self.poke(addr, self.cpu.acc & self.cpu.xr),
+            Mnemonic::Las => {
+                let value = self.peek(addr) & self.cpu.sp;
+                self.cpu.sp = value;
+                self.cpu.do_lax(value);
             }
-            0xCD => { // CMP absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xD9 => { // CMP absolute,Y
-                let addr = self.get_addr_absolute_y();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xDD => { // CMP absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xC1 => { // CMP (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0xD1 => { // CMP (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.acc, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
+            Mnemonic::Sha => self.store_high_and(addr, self.cpu.yr, self.cpu.acc & self.cpu.xr),
+            Mnemonic::Shx => self.store_high_and(addr, self.cpu.yr, self.cpu.xr),
+            Mnemonic::Shy => self.store_high_and(addr, self.cpu.xr, self.cpu.yr),
+            Mnemonic::Tas => {
+                self.cpu.sp = self.cpu.acc & self.cpu.xr;
+                self.store_high_and(addr, self.cpu.yr, self.cpu.sp);
             }
 
-            0xE0 => { // CPX #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_cmp(self.cpu.xr, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0xE4 => { // CPX zeropage
-                let addr = self.get_addr_zeropage();
+            // Undocumented immediate operations
+            Mnemonic::Anc => { let value = self.peek(addr); self.cpu.do_anc(value); }
+            Mnemonic::Alr => { let value = self.peek(addr); self.cpu.do_alr(value); }
+            Mnemonic::Arr => { let value = self.peek(addr); self.cpu.do_arr(value); }
+            Mnemonic::Sbx => { let value = self.peek(addr); self.cpu.do_sbx(value); }
+            Mnemonic::Ane => {
+                // Unstable, uses the common 0xEE magic constant
                 let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.xr, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
+                self.cpu.lda((self.cpu.acc | 0xEE) & self.cpu.xr & value);
             }
-            0xEC => { // CPX absolute
-                let addr = self.get_addr_absolute();
+            Mnemonic::Lxa => {
+                // Unstable, uses the common 0xEE magic constant
                 let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.xr, value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
+                self.cpu.do_lax((self.cpu.acc | 0xEE) & value);
             }
 
-            0xC0 => { // CPY #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_cmp(self.cpu.yr, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0xC4 => { // CPY zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.yr, value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0xCC => { // CPY absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_cmp(self.cpu.yr, value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
+            // JAM - halts the CPU, PC stays on the opcode until reset
+            Mnemonic::Jam => {
+                self.cpu.pc = self.cpu.pc.wrapping_sub(1);
+                self.cpu.jammed = true;
             }
+        }
 
-            // Arithmetic operations
-            0x69 => { // ADC #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0x65 => { // ADC zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x75 => { // ADC zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
+        if op.page_penalty && self.page_crossed {
+            self.clock_ticks += 1;
+        }
+    }
+
+    // Render one raster line
+    // Frame buffer column the TED beam has reached after `cycles` double-clock
+    // cycles of the current line
+    fn beam_x(cycles: u32) -> usize {
+        ((cycles.saturating_sub(FRAME_FIRST_CYCLE) * 4) as usize).min(FRAME_WIDTH)
+    }
+
+    // Draw the current line up to the beam position. Called before a write
+    // to a video register, so the pixels already passed by the beam keep the
+    // old register values.
+    fn render_to_beam(&mut self) {
+        let cost = if self.is_single_clock() { 2 } else { 1 };
+        let cycles = self.clock_counter + self.write_delay * cost;
+        self.render_line_segment(Self::beam_x(cycles));
+    }
+
+    // Draw pixels line_x..end_x of the current line with the current register
+    // values. The whole line is rendered, then the pixels drawn by earlier
+    // segments are restored.
+    fn render_line_segment(&mut self, end_x: usize) {
+        // Raster line shown at the top of the frame (the top border wraps
+        // around from the end of the previous frame)
+        let first_frame_line = self.raster_lines + FIRST_SCREEN_LINE as u32 - BORDER_TOP as u32;
+        let frame_line = ((self.raster_line + self.raster_lines - first_frame_line) % self.raster_lines) as usize;
+        if end_x <= self.line_x || frame_line >= self.frame_height() {
+            return;
+        }
+
+        let drawn = self.pixels[frame_line];
+        self.render_frame_line(frame_line);
+        self.pixels[frame_line][..self.line_x].copy_from_slice(&drawn[..self.line_x]);
+        self.line_x = end_x;
+    }
+
+    fn render_frame_line(&mut self, frame_line: usize) {
+        // Paint the whole frame line with the border color from 0xFF19,
+        // then draw the display window over it
+        let border_color = self.ram[0xFF19] & 0x7F;
+        self.pixels[frame_line].fill(border_color);
+
+        if self.raster_line >= FIRST_SCREEN_LINE as u32
+            && self.raster_line < (FIRST_SCREEN_LINE + SCREEN_HEIGHT) as u32
+        {
+            // Check if screen is on (bit 4 of 0xFF06). A blanked screen
+            // shows only the border color.
+            let screen_on = (self.ram[0xFF06] & 16) != 0;
+
+            // 24-row mode (bit 3 of 0xFF06 clear) narrows the window by
+            // 4 lines at the top and bottom
+            let window_line = (self.raster_line - FIRST_SCREEN_LINE as u32) as usize;
+            let rows_25 = (self.ram[0xFF06] & 8) != 0;
+            let in_window = rows_25 || (4..SCREEN_HEIGHT - 4).contains(&window_line);
+
+            if screen_on && in_window {
+                self.render_display_line(frame_line);
             }
-            0x6D => { // ADC absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x7D => { // ADC absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x79 => { // ADC absolute,Y
-                let addr = self.get_addr_absolute_y();
-                let value = self.peek(addr);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0x61 => { // ADC (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                let value = self.peek(addr);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x71 => { // ADC (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                let value = self.peek(addr);
-                self.cpu.do_adc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-
-            0xE9 => { // SBC #immediate
-                let value = self.peek(self.cpu.pc + 1);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 2;
-            }
-            0xE5 => { // SBC zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0xF5 => { // SBC zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 4;
-            }
-            0xED => { // SBC absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xFD => { // SBC absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xF9 => { // SBC absolute,Y
-                let addr = self.get_addr_absolute_y();
-                let value = self.peek(addr);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-            0xE1 => { // SBC (indirect,X)
-                let addr = self.get_addr_indirect_x();
-                let value = self.peek(addr);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0xF1 => { // SBC (indirect),Y
-                let addr = self.get_addr_indirect_y();
-                let value = self.peek(addr);
-                self.cpu.do_sbc(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-
-            // Shift/Rotate operations
-            0x0A => { // ASL accumulator
-                self.cpu.acc = self.cpu.do_asl(self.cpu.acc);
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 2;
-            }
-            0x06 => { // ASL zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                let result = self.cpu.do_asl(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-            0x16 => { // ASL zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_asl(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x0E => { // ASL absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                let result = self.cpu.do_asl(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 6;
-            }
-            0x1E => { // ASL absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_asl(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 7;
-            }
-            0x4A => { // LSR accumulator
-                self.cpu.acc = self.cpu.do_lsr(self.cpu.acc);
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 2;
-            }
-            0x46 => { // LSR zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                let result = self.cpu.do_lsr(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-            0x56 => { // LSR zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_lsr(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x4E => { // LSR absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                let result = self.cpu.do_lsr(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 6;
-            }
-            0x5E => { // LSR absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_lsr(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 7;
-            }
-            0x2A => { // ROL accumulator
-                self.cpu.acc = self.cpu.do_rol(self.cpu.acc);
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 2;
-            }
-            0x26 => { // ROL zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                let result = self.cpu.do_rol(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-            0x36 => { // ROL zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_rol(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x2E => { // ROL absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                let result = self.cpu.do_rol(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 6;
-            }
-            0x3E => { // ROL absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_rol(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 7;
-            }
-            0x6A => { // ROR accumulator
-                self.cpu.acc = self.cpu.do_ror(self.cpu.acc);
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 2;
-            }
-            0x66 => { // ROR zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                let result = self.cpu.do_ror(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-            0x76 => { // ROR zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_ror(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0x6E => { // ROR absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                let result = self.cpu.do_ror(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 6;
-            }
-            0x7E => { // ROR absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                let result = self.cpu.do_ror(value);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 7;
-            }
-
-            // INC - Increment Memory
-            0xE6 => { // INC zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                let result = value.wrapping_add(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-            0xF6 => { // INC zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                let result = value.wrapping_add(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0xEE => { // INC absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                let result = value.wrapping_add(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 6;
-            }
-            0xFE => { // INC absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                let result = value.wrapping_add(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 7;
-            }
-
-            // DEC - Decrement Memory
-            0xC6 => { // DEC zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                let result = value.wrapping_sub(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 5;
-            }
-            0xD6 => { // DEC zeropage,X
-                let addr = self.get_addr_zeropage_x();
-                let value = self.peek(addr);
-                let result = value.wrapping_sub(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 6;
-            }
-            0xCE => { // DEC absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                let result = value.wrapping_sub(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 6;
-            }
-            0xDE => { // DEC absolute,X
-                let addr = self.get_addr_absolute_x();
-                let value = self.peek(addr);
-                let result = value.wrapping_sub(1);
-                self.cpu.neg_flag(result);
-                self.cpu.zero_flag(result);
-                self.poke(addr, result);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 7;
-            }
-
-            // Bit test
-            0x24 => { // BIT zeropage
-                let addr = self.get_addr_zeropage();
-                let value = self.peek(addr);
-                self.cpu.do_bit(value);
-                self.cpu.incr_pc(2);
-                self.clock_ticks = 3;
-            }
-            0x2C => { // BIT absolute
-                let addr = self.get_addr_absolute();
-                let value = self.peek(addr);
-                self.cpu.do_bit(value);
-                self.cpu.incr_pc(3);
-                self.clock_ticks = 4;
-            }
-
-            // Jumps and branches
-            0x4C => { // JMP absolute
-                self.cpu.pc = self.get_addr_absolute();
-                self.clock_ticks = 3;
-            }
-            0x6C => { // JMP indirect
-                let addr = self.get_addr_absolute();
-                let lo = self.peek(addr) as u16;
-                let hi = self.peek(addr.wrapping_add(1)) as u16;
-                self.cpu.pc = (hi << 8) | lo;
-                self.clock_ticks = 5;
-            }
-
-            0x20 => { // JSR
-                let target = self.get_addr_absolute();
-                let ret_addr = self.cpu.pc.wrapping_add(2);
-                self.push_word(ret_addr);
-                self.cpu.pc = target;
-                self.clock_ticks = 6;
-            }
-            0x60 => { // RTS
-                let addr = self.pull_word();
-                self.cpu.pc = addr.wrapping_add(1);
-                self.clock_ticks = 6;
-            }
-            0x40 => { // RTI
-                let flags = self.pull();
-                self.set_flags(flags);
-                self.cpu.pc = self.pull_word();
-                self.clock_ticks = 6;
-                self.cpu.i = false;
-            }
-
-            // Branch instructions
-            0x90 => { // BCC
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if !self.cpu.c {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-            0xB0 => { // BCS
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if self.cpu.c {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-            0xF0 => { // BEQ
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if self.cpu.z {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-            0xD0 => { // BNE
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if !self.cpu.z {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-            0x30 => { // BMI
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if self.cpu.n {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-            0x10 => { // BPL
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if !self.cpu.n {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-            0x50 => { // BVC
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if !self.cpu.v {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-            0x70 => { // BVS
-                let offset = self.peek(self.cpu.pc + 1) as i8;
-                self.cpu.incr_pc(2);
-                if self.cpu.v {
-                    self.cpu.pc = (self.cpu.pc as i32 + offset as i32) as u16;
-                    self.clock_ticks = 3;
-                } else {
-                    self.clock_ticks = 2;
-                }
-            }
-
-            // Flag operations
-            0x18 => { self.cpu.c = false; self.cpu.incr_pc(1); self.clock_ticks = 2; } // CLC
-            0x38 => { self.cpu.c = true; self.cpu.incr_pc(1); self.clock_ticks = 2; } // SEC
-            0x58 => { self.cpu.i = false; self.cpu.incr_pc(1); self.clock_ticks = 2; } // CLI
-            0x78 => { self.cpu.i = true; self.cpu.incr_pc(1); self.clock_ticks = 2; } // SEI
-            0xD8 => { self.cpu.d = false; self.cpu.incr_pc(1); self.clock_ticks = 2; } // CLD
-            0xF8 => { self.cpu.d = true; self.cpu.incr_pc(1); self.clock_ticks = 2; } // SED
-            0xB8 => { self.cpu.v = false; self.cpu.incr_pc(1); self.clock_ticks = 2; } // CLV
-
-            // NOP and illegal opcodes
-            0xEA | 0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA | 0xFC | 0x3F | 0x7F | 0x07 => { // NOP
-                self.cpu.incr_pc(1);
-                self.clock_ticks = 2;
+        }
+    }
+
+    // Finish the current raster line and move to the next one
+    fn render_raster_line(&mut self) {
+        self.render_line_segment(FRAME_WIDTH);
+        self.line_x = 0;
+
+        // The counter runs up to 511 if it was written past the last line
+        self.raster_line += 1;
+        if self.raster_line == self.raster_lines || self.raster_line >= 512 {
+            self.raster_line = 0;
+            self.frame_counter = (self.frame_counter + 1) & 0x0F;
+            if self.frame_counter == 0 {
+                self.flash_phase = !self.flash_phase;
             }
+            self.frame_done = true;
+            self.write_recording();
+        }
 
-            0x00 => { // BRK
-                self.cpu.i = true;
-                self.cpu.b = true;
-                self.push_word(self.cpu.pc + 2);
-                self.push(self.get_flags());
-                let irq_lo = self.rom[0xFFFE - 0x8000] as u16;
-                let irq_hi = self.rom[0xFFFF - 0x8000] as u16;
-                self.cpu.pc = (irq_hi << 8) | irq_lo;
-                self.clock_ticks = 7;
-            }
+        // Raster interrupt handling
+        // Raster interrupt line is 9-bit: bit 0 of 0xFF0A (high bit) + 0xFF0B (low byte)
+        let raster_interrupt_line = (((self.ram[0xFF0A] & 1) as u32) << 8) + self.ram[0xFF0B] as u32;
+        // println!("Raster Line: {}, Interrupt Line: {}, Register 0xFF06: 0x{:02X}", self.raster_line, raster_interrupt_line, self.ram[0xFF06]);
 
-            _ => {
-                // Unimplemented opcode
-                println!("Unimplemented opcode: 0x{:02X} at PC=0x{:04X}", opcode, self.cpu.pc);
-                self.cpu.incr_pc(1);
-            }
+        if self.raster_line == raster_interrupt_line {
+            self.request_interrupt(0x02);
         }
     }
 
-    // Render one raster line
-    fn render_raster_line(&mut self) {
-        if self.raster_line >= FIRST_SCREEN_LINE as u32
-            && self.raster_line < (FIRST_SCREEN_LINE + SCREEN_HEIGHT) as u32
-        {
-            let line = (self.raster_line - FIRST_SCREEN_LINE as u32) as usize;
-
-            // Check if screen is on (bit 4 of 0xFF06)
-            let screen_on = (self.ram[0xFF06] & 16) != 0;
-
-            if screen_on {
-                // Check if we're in bitmap mode (bit 5 of 0xFF06)
-                let hires_mode = (self.ram[0xFF06] & 32) != 0;
-                // let multicolor_mode = (self.ram[0xFF06] & 64) != 0;
-
-                if hires_mode {
-                    // Bitmap mode rendering (Hi-Res graphics)
-                    self.render_bitmap_line(line);
-                } else {
-                    // Text mode rendering (40x25 characters, 8x8 pixels each)
-                    self.render_text_line(line);
-                }
+    // Draw the display window of one frame row, applying the fine scroll
+    // offsets and the 38-column border
+    fn render_display_line(&mut self, row: usize) {
+        let bg_color = self.ram[0xFF15] & 0x7F;
+        let window = BORDER_LEFT..BORDER_LEFT + SCREEN_WIDTH;
+
+        // YSCROLL (bits 0-2 of 0xFF06) moves the content down. With the
+        // default of 3 the first content line starts at the window top.
+        let yscroll = (self.ram[0xFF06] & 7) as u32;
+        let line = self.raster_line.wrapping_sub(yscroll) as usize;
+
+        if line < SCREEN_HEIGHT {
+            // Check if we're in bitmap mode (bit 5 of 0xFF06)
+            let hires_mode = (self.ram[0xFF06] & 32) != 0;
+            // Multicolor mode (bit 4 of 0xFF07)
+            let multicolor_mode = (self.ram[0xFF07] & 16) != 0;
+            // Extended color mode (bit 6 of 0xFF06)
+            let ecm_mode = (self.ram[0xFF06] & 64) != 0;
+
+            if ecm_mode && (hires_mode || multicolor_mode) {
+                // ECM combined with bitmap or multicolor is invalid and
+                // produces black output on real hardware
+                self.pixels[row][window.clone()].fill(0);
+            } else if hires_mode && multicolor_mode {
+                self.render_multicolor_bitmap_line(line, row);
+            } else if hires_mode {
+                // Bitmap mode rendering (Hi-Res graphics)
+                self.render_bitmap_line(line, row);
             } else {
-                // Screen is blanked - fill with frame color from register 0xFF19
-                let frame_color = self.ram[0xFF19] & 0x7F;
-                for x in 0..SCREEN_WIDTH {
-                    self.pixels[line][x] = frame_color;
-                }
+                // Text mode rendering (40x25 characters, 8x8 pixels each)
+                self.render_text_line(line, row);
             }
+        } else {
+            // Lines scrolled out of the content show the background
+            self.pixels[row][window.clone()].fill(bg_color);
         }
 
-        self.raster_line += 1;
-        if self.raster_line >= RASTER_LINES {
-            self.raster_line = 0;
+        // XSCROLL (bits 0-2 of 0xFF07) moves the content right, uncovering
+        // background pixels on the left
+        let xscroll = (self.ram[0xFF07] & 7) as usize;
+        if xscroll > 0 {
+            let pixels = &mut self.pixels[row][window];
+            pixels.copy_within(0..SCREEN_WIDTH - xscroll, xscroll);
+            pixels[..xscroll].fill(bg_color);
         }
 
-        // Raster interrupt handling
-        // Raster interrupt line is 9-bit: bit 0 of 0xFF0A (high bit) + 0xFF0B (low byte)
-        let raster_interrupt_line = (((self.ram[0xFF0A] & 1) as u32) << 8) + self.ram[0xFF0B] as u32;
-        // println!("Raster Line: {}, Interrupt Line: {}, Register 0xFF06: 0x{:02X}", self.raster_line, raster_interrupt_line, self.ram[0xFF06]);
-
-        if self.raster_line == raster_interrupt_line {
-            // Set raster interrupt bit (bit 1) + master interrupt flag (bit 7)
-            self.ram[0xFF09] |= 2 + 128;
-
-            // println!("-------> Raster Interrupt Triggered at line {}", self.raster_line);
-            // Check if interrupt should trigger: interrupt flag clear AND raster interrupt enabled in mask
-            if !self.cpu.i && (self.ram[0xFF0A] & 2) != 0 {
-                self.push_word(self.cpu.pc);
-                self.push(self.get_flags());
-
-                self.cpu.i = true;
+        // 38-column mode (bit 3 of 0xFF07 clear): the border covers the
+        // first and last 8 pixels of the window
+        if (self.ram[0xFF07] & 8) == 0 {
+            let border_color = self.ram[0xFF19] & 0x7F;
+            self.pixels[row][BORDER_LEFT..BORDER_LEFT + 8].fill(border_color);
+            self.pixels[row][BORDER_LEFT + SCREEN_WIDTH - 8..BORDER_LEFT + SCREEN_WIDTH].fill(border_color);
+        }
+    }
 
-                // Jump to IRQ vector at 0xFFFE/0xFFFF
-                // let irq_vec = self.rom[0xFFFE - 0x8000] as u16 | ((self.rom[0xFFFF - 0x8000] as u16) << 8);
-                let irq_lo = self.peek(0xFFFE) as u16;
-                let irq_hi = self.peek(0xFFFF) as u16;
-                // self.cpu.pc = irq_vec;
-                self.cpu.pc = irq_lo | (irq_hi << 8);
-                // let irq_lo = self.rom[0xFFFE - 0x8000] as u16;
-                // let irq_hi = self.rom[0xFFFF - 0x8000] as u16;
-                // self.cpu.pc = irq_lo | (irq_hi << 8);
-            }
+    // Per-cell bitmap colors from the video matrix, as [clear, set] pixels.
+    // The color nibbles come from the screen byte (video matrix + 1024) and
+    // the luminance nibbles from the attribute byte (video matrix): set
+    // pixels use the low color nibble and attribute bits 4-6, clear pixels
+    // the high color nibble and attribute bits 0-2.
+    fn bitmap_cell_colors(&self, cell: usize) -> [u8; 2] {
+        let video_matrix_address = ((self.ram[0xFF14] & 0xF8) as usize) << 8;
+        let attribute = self.ted_ram(video_matrix_address + cell);
+        let screen = self.ted_ram(video_matrix_address + 1024 + cell);
 
-        // if (rasterLine == rasterInterruptLine) {
-        //     ram[0xff09] |= 2+128;
+        [
+            (screen >> 4) | ((attribute & 0x07) << 4),
+            (screen & 0x0F) | (attribute & 0x70),
+        ]
+    }
 
-        //     if (!cpu.i && (ram[0xff0a] & 2)  > 0) {
-        //         Dush(cpu.pc);                  // Register sichern
-        //         Push(GetFlags());
-        //         cpu.i = true;                     // IRQs abschalten
-        //         cpu.pc = (rom[0xffff - 0x8000] << 8) + rom[0xfffe - 0x8000];
-        //     }
-        // }
-        }
+    // Bitmap base address from register 0xFF12 (bits 3-5)
+    fn bitmap_base(&self) -> usize {
+        (((self.ram[0xFF12] >> 3) & 7) as usize) * 0x2000
     }
 
     // Render one line of bitmap mode (Hi-Res graphics: 320x200 pixels)
-    fn render_bitmap_line(&mut self, line: usize) {
-        // Background color from TED register 0xFF15
-        let bg_color = self.ram[0xFF15] & 0x7F;
+    fn render_bitmap_line(&mut self, line: usize, row: usize) {
+        // Bitmap layout in Plus/4:
+        // The screen is divided into 40x25 character blocks (8x8 pixels each).
+        // Each block is 8 consecutive bytes, one per pixel row, with bit 7 as
+        // the leftmost pixel.
+        let hires_base = self.bitmap_base();
+        let block_row = line / 8;           // Which row of 8x8 blocks (0-24)
+        let pixel_in_block_y = line & 7;    // Which pixel row within the block (0-7)
 
-        // Bitmap base address from register 0xFF12 (bits 3-7)
-        // Formula: (ram[0xFF12] >> 3) * 0x2000
-        let hires_base = ((self.ram[0xFF12] >> 3) as usize) * 0x2000;
+        for block_col in 0..40 {
+            let [clear_color, set_color] = self.bitmap_cell_colors(block_row * 40 + block_col);
 
-        // Bitmap layout in Plus/4:
-        // The screen is divided into 40x25 character blocks (8x8 pixels each)
-        // For each pixel at position (line, x):
-        // - Calculate which 8x8 block it belongs to
-        // - Read the corresponding byte from bitmap memory
-        // - Check the specific bit for this pixel
-        // - If bit is 1: use foreground color from color RAM at 0x1800
-        // - If bit is 0: use background color from 0xFF15
-
-        for x in 0..SCREEN_WIDTH {
-            // Calculate bitmap memory address for this pixel
             // Formula: hiresBase + (pixelY/8)*320 + (pixelY & 7) + (x/8)*8
-            let block_row = line / 8;           // Which row of 8x8 blocks (0-24)
-            let pixel_in_block_y = line & 7;    // Which pixel row within the block (0-7)
-            let block_col = x / 8;              // Which column of 8x8 blocks (0-39)
-            let pixel_in_block_x = x & 7;       // Which pixel column within the block (0-7)
-
-            // Bitmap address calculation
             let bitmap_addr = hires_base + block_row * 320 + pixel_in_block_y + block_col * 8;
+            let bitmap_byte = self.ted_ram(bitmap_addr);
+
+            for bit in 0..8 {
+                let pixel_set = (bitmap_byte & (0x80 >> bit)) != 0;
+                self.pixels[row][BORDER_LEFT + block_col * 8 + bit] = if pixel_set { set_color } else { clear_color };
+            }
+        }
+    }
 
-            // Read the bitmap byte (wraps at 64KB boundary)
-            let bitmap_byte = self.ram[bitmap_addr & 0xFFFF];
+    // Multicolor bitmap: 00 and 11 come from 0xFF15/0xFF16, 01 and 10 use
+    // the clear and set colors of the cell
+    fn render_multicolor_bitmap_line(&mut self, line: usize, row: usize) {
+        let hires_base = self.bitmap_base();
+        let block_row = line / 8;
+        let pixel_in_block_y = line & 7;
+
+        for block_col in 0..40 {
+            let [clear_color, set_color] = self.bitmap_cell_colors(block_row * 40 + block_col);
+            let colors = [
+                self.ram[0xFF15] & 0x7F,
+                clear_color,
+                set_color,
+                self.ram[0xFF16] & 0x7F,
+            ];
 
-            // Check if the specific bit is set (bit 7 is leftmost pixel, bit 0 is rightmost)
-            let bit_mask = 1 << (7 - pixel_in_block_x);
-            let pixel_set = (bitmap_byte & bit_mask) != 0;
+            let bitmap_addr = hires_base + block_row * 320 + pixel_in_block_y + block_col * 8;
+            let bitmap_byte = self.ted_ram(bitmap_addr);
 
-            if pixel_set {
-                // Pixel is set: use foreground color from color RAM
-                // Color RAM address: 0x1800 + (pixelY/8)*40 + (x/8)
-                let color_addr = 0x1800 + block_row * 40 + block_col;
-                let color = self.ram[color_addr] & 0x7F;
-                self.pixels[line][x] = color;
-            } else {
-                // Pixel is not set: use background color
-                self.pixels[line][x] = bg_color;
+            // Each pair of bits selects one of four colors for two pixels
+            for pair in 0..4 {
+                let color = colors[((bitmap_byte >> (6 - pair * 2)) & 3) as usize];
+                let x = BORDER_LEFT + block_col * 8 + pair * 2;
+                self.pixels[row][x] = color;
+                self.pixels[row][x + 1] = color;
             }
         }
     }
 
-    // Render one line of text mode (40 characters wide, 8 pixels per character)
-    fn render_text_line(&mut self, line: usize) {
+    // Flash phase for flashing characters and the cursor
+    fn flash_on(&self) -> bool {
+        self.flash_phase
+    }
+
+    // Character generator byte from RAM, or from the currently banked ROM
+    // when bit 2 of 0xFF12 selects it
+    fn read_char_data(&self, addr: usize, from_rom: bool) -> u8 {
+        if from_rom {
+            if addr >= 0x8000 { self.read_rom(addr & 0xFFFF) } else { 0 }
+        } else {
+            self.ted_ram(addr)
+        }
+    }
+
+    fn render_text_line(&mut self, line: usize, row: usize) {
         let char_row = line / 8; // Which character row (0-24)
         let pixel_row = line % 8; // Which pixel row within the character (0-7)
 
@@ -1563,176 +1209,299 @@ impl Plus4 {
         // Check if using 256 character set (bit 7 of 0xFF07)
         let charset_256 = (self.ram[0xFF07] & 128) != 0;
 
-        // Get cursor position from TED registers 0xFF0C (high) and 0xFF0D (low)
-        let cursor_address = ((self.ram[0xFF0C] as usize) << 8) | (self.ram[0xFF0D] as usize);
-        let hires_mode = (self.ram[0xFF06] & 32) != 0;
+        // Get cursor position from TED registers 0xFF0C (bits 8-9) and 0xFF0D (low)
+        let cursor_address = (((self.ram[0xFF0C] & 3) as usize) << 8) | (self.ram[0xFF0D] as usize);
+        let flash_on = self.flash_on();
+        let multicolor_mode = (self.ram[0xFF07] & 16) != 0;
+        let ecm_mode = (self.ram[0xFF06] & 64) != 0;
 
         for col in 0..40 {
             let screen_offset = col + char_row * 40;
 
             // Character code from video_matrix + 1024
-            let char_addr = (video_matrix_address + 1024 + screen_offset) & 0xFFFF;
-            let mut char_code = self.ram[char_addr] as usize;
-
-            // Check if this is the cursor position and flash is on
-            let is_cursor_char = cursor_address == screen_offset && self.flash_on && !hires_mode;
-            if is_cursor_char {
-                // Invert character code by XORing with 0x80
-                char_code ^= 0x80;
+            let mut char_code = self.ted_ram(video_matrix_address + 1024 + screen_offset) as usize;
+
+            // In extended color mode the top two bits of the character code
+            // select the background from 0xFF15-0xFF18, leaving 64 characters
+            let mut char_bg = bg_color;
+            if ecm_mode {
+                char_bg = self.ram[0xFF15 + (char_code >> 6)] & 0x7F;
+                char_code &= 0x3F;
             }
 
-            // Get color for this character from video_matrix
-            let color_addr = (video_matrix_address + screen_offset) & 0xFFFF;
-            let color = self.ram[color_addr] & 0x7F; // 7 bits for color
+            // Hardware reverse mode (bit 7 of 0xFF07 clear): characters
+            // 128-255 show characters 0-127 inverted
+            let reverse = !charset_256 && char_code > 127;
+            if reverse {
+                char_code -= 128;
+            }
+
+            // Color attribute from video_matrix: bits 0-6 color, bit 7 flashing
+            let attribute = self.ted_ram(video_matrix_address + screen_offset);
+            let color = attribute & 0x7F;
+            let flashing = (attribute & 0x80) != 0;
 
             // Get character bitmap
-            let char_data = if charset_256 {
-                // 256 character mode
-                let addr = charset_base + char_code * 8 + pixel_row;
-                if charset_from_rom {
-                    let rom_offset = addr.wrapping_sub(0x8000);
-                    if rom_offset < self.rom.len() {
-                        self.rom[rom_offset]
-                    } else {
-                        0
-                    }
-                } else {
-                    self.ram[addr & 0xFFFF]
-                }
-            } else {
-                // 128 character mode - invert for codes > 127
-                if char_code > 127 {
-                    let addr = charset_base + (char_code - 128) * 8 + pixel_row;
-                    let data = if charset_from_rom {
-                        let rom_offset = addr.wrapping_sub(0x8000);
-                        if rom_offset < self.rom.len() {
-                            self.rom[rom_offset]
-                        } else {
-                            0
-                        }
-                    } else {
-                        self.ram[addr & 0xFFFF]
-                    };
-                    !data // Invert
-                } else {
-                    let addr = charset_base + char_code * 8 + pixel_row;
-                    if charset_from_rom {
-                        let rom_offset = addr.wrapping_sub(0x8000);
-                        if rom_offset < self.rom.len() {
-                            self.rom[rom_offset]
-                        } else {
-                            0
-                        }
-                    } else {
-                        self.ram[addr & 0xFFFF]
-                    }
+            let mut char_data = self.read_char_data(charset_base + char_code * 8 + pixel_row, charset_from_rom);
+            if reverse {
+                char_data = !char_data;
+            }
+
+            // Flashing characters show only their background while the
+            // flash counter is in its off phase
+            if flashing && !flash_on {
+                char_data = 0;
+            }
+
+            // The cursor inverts the character it is on, blinking with the
+            // flash counter
+            if cursor_address == screen_offset && flash_on {
+                char_data = !char_data;
+            }
+
+            // In multicolor mode, characters with bit 3 of their color set
+            // are drawn with double-width pixels: 00 background, 01 0xFF16,
+            // 10 0xFF17 and 11 the character color without bit 3
+            if multicolor_mode && (color & 0x08) != 0 {
+                let colors = [
+                    bg_color,
+                    self.ram[0xFF16] & 0x7F,
+                    self.ram[0xFF17] & 0x7F,
+                    color & 0x77,
+                ];
+                for pair in 0..4 {
+                    let pixel_color = colors[((char_data >> (6 - pair * 2)) & 3) as usize];
+                    let x = BORDER_LEFT + col * 8 + pair * 2;
+                    self.pixels[row][x] = pixel_color;
+                    self.pixels[row][x + 1] = pixel_color;
                 }
-            };
+                continue;
+            }
 
             // Render 8 pixels for this character
             for bit in 0..8 {
-                let x = col * 8 + bit;
-                if x < SCREEN_WIDTH {
-                    let pixel_set = (char_data & (0x80 >> bit)) != 0;
-                    self.pixels[line][x] = if pixel_set { color } else { bg_color };
-                }
+                let pixel_set = (char_data & (0x80 >> bit)) != 0;
+                self.pixels[row][BORDER_LEFT + col * 8 + bit] =
+                    if pixel_set { color } else { char_bg };
             }
         }
     }
 
+    // Record the instruction at PC if a tracer is attached and its filter matches
+    fn trace_instruction(&mut self) {
+        let pc = self.cpu.pc;
+        let in_rom = self.rom_visible(pc);
+        let wanted = self.tracer.as_mut().is_some_and(|tracer| tracer.should_trace(pc, in_rom));
+        if !wanted {
+            return;
+        }
+
+        let entry = TraceEntry {
+            pc,
+            opcode: self.peek(pc),
+            disassembly: disassembler::disassemble_one(|a| self.peek(a), pc, &self.symbols).to_string(),
+            acc: self.cpu.acc,
+            xr: self.cpu.xr,
+            yr: self.cpu.yr,
+            sp: self.cpu.sp,
+            flags: self.get_flags(),
+            cycles: self.cycle_count,
+            raster_line: self.raster_line,
+        };
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.record(entry);
+        }
+    }
+
     // Main emulation step
     pub fn step(&mut self) {
-        self.clock_ticks = 0;
-        self.execute_instruction();
+        // An interrupt is taken in place of the next instruction
+        self.clock_ticks = self.service_interrupt();
+        if self.clock_ticks == 0 {
+            if self.tracer.is_some() {
+                self.trace_instruction();
+            }
+            self.execute_instruction();
+        }
+        self.cycle_count += self.clock_ticks as u64;
 
-        let clock_multiplier = if (self.ram[0xFF06] & 16) != 0 { 2 } else { 1 };
+        // Advance TED through the CPU cycles of the instruction. Raster
+        // lines are rendered as they complete.
+        for _ in 0..self.clock_ticks {
+            self.cpu_cycle();
+        }
+    }
+
+    // Raster line with character or bitmap fetches
+    fn is_display_line(&self) -> bool {
+        (self.ram[0xFF06] & 16) != 0
+            && self.raster_line >= FIRST_SCREEN_LINE as u32
+            && self.raster_line < (FIRST_SCREEN_LINE + SCREEN_HEIGHT) as u32
+    }
 
-        self.clock_counter += self.clock_ticks * clock_multiplier;
+    // Display line on which TED also fetches the attributes of a new
+    // character row, which happens when the low raster bits match YSCROLL
+    fn is_badline(&self) -> bool {
+        self.is_display_line() && (self.raster_line & 7) == (self.ram[0xFF06] & 7) as u32
+    }
+
+    fn in_fetch_window(&self) -> bool {
+        (FETCH_WINDOW_START..FETCH_WINDOW_END).contains(&self.clock_counter)
+    }
 
-        // Flash counter for cursor blink
-        self.flash_counter += self.clock_ticks;
-        if self.flash_counter >= TICKS_PER_BLINK_INTERVAL {
-            self.flash_counter = 0;
-            self.flash_on = !self.flash_on;
+    // The CPU runs single-clock while TED fetches display data, and always
+    // when bit 1 of 0xFF13 forces it
+    fn is_single_clock(&self) -> bool {
+        (self.ram[0xFF13] & 2) != 0 || (self.is_display_line() && self.in_fetch_window())
+    }
+
+    // Run TED for one CPU cycle, including any cycles stolen by badline DMA
+    fn cpu_cycle(&mut self) {
+        while self.is_badline() && self.in_fetch_window() {
+            self.ted_cycle();
         }
 
-        // TED Timer A, B & C countdown
-        for timer_idx in 0..3 {
-            self.timer_overflow[timer_idx] = false;
+        let cost = if self.is_single_clock() { 2 } else { 1 };
+        for _ in 0..cost {
+            self.ted_cycle();
+        }
+    }
 
-            if self.timer_on[timer_idx] {
-                // Read 16-bit timer value from registers
-                let timer_value = self.ram[0xFF00 + timer_idx*2] as u16
-                    + ((self.ram[0xFF01 + timer_idx*2] as u16) << 8);
+    // One double-clock cycle of TED
+    fn ted_cycle(&mut self) {
+        self.sound.tick();
+        // The timers count single-clock cycles
+        if self.double_cycles.is_multiple_of(2) {
+            let bits = self.timers.tick();
+            if bits != 0 {
+                self.request_interrupt(bits);
+            }
+        }
+        self.double_cycles += 1;
+        self.clock_counter += 1;
+        if self.clock_counter >= TICKS_PER_RASTER_LINE {
+            self.clock_counter = 0;
+            self.render_raster_line();
+        }
+    }
 
-                // Check if timer underflows
-                let ticks = (self.clock_ticks * clock_multiplier) as u16;
-                self.timer_overflow[timer_idx] = timer_value < ticks;
+    // Interrupt controller. Sources latch their bits in IRR (0xFF09) until
+    // the program acknowledges them; the IRQ line stays asserted as long as
+    // a latched source is enabled in IMR (0xFF0A).
 
-                // Subtract clock ticks from timer value (with wrapping)
-                let mut new_timer_value = timer_value.wrapping_sub(ticks);
+    // Latch an interrupt source in IRR
+    fn request_interrupt(&mut self, bits: u8) {
+        self.ram[0xFF09] |= bits & IRQ_SOURCES;
+        self.update_irq_line();
+    }
 
-                // Special handling for Timer A: subtract additional 0xC60E on overflow
-                if self.timer_overflow[timer_idx] && timer_idx == 0 {
-                    new_timer_value = new_timer_value.wrapping_sub(0xC60E);
-                }
+    // IRR bit 7 mirrors the IRQ line
+    fn update_irq_line(&mut self) {
+        if self.irq_asserted() {
+            self.ram[0xFF09] |= 0x80;
+        } else {
+            self.ram[0xFF09] &= !0x80;
+        }
+    }
 
-                // Write updated timer value back to registers
-                self.ram[0xFF00 + timer_idx*2] = (new_timer_value & 0xFF) as u8;
-                self.ram[0xFF01 + timer_idx*2] = ((new_timer_value >> 8) & 0xFF) as u8;
-            }
+    fn irq_asserted(&self) -> bool {
+        (self.ram[0xFF09] & self.ram[0xFF0A] & IRQ_SOURCES) != 0
+    }
+
+    // Checked at instruction boundaries: enter the IRQ handler while the
+    // line is asserted and interrupts are enabled. Returns the cycles spent.
+    fn service_interrupt(&mut self) -> u32 {
+        if self.cpu.i || self.cpu.jammed || !self.irq_asserted() {
+            return 0;
         }
 
-        // println!("Timer A: {:04X}, Timer B: {:04X}, Timer C: {:04X}", 
-        //     self.ram[0xFF00] as u16 + ((self.ram[0xFF01] as u16) << 8),
-        //     self.ram[0xFF02] as u16 + ((self.ram[0xFF03] as u16) << 8),
-        //     self.ram[0xFF04] as u16 + ((self.ram[0xFF05] as u16) << 8)
-        // );
-
-        // Generate IRQs for timer overflows
-        for timer_idx in 0..3 {
-            if self.timer_on[timer_idx] && self.timer_overflow[timer_idx] {
-                // Determine which IRR bit to set
-                let bit = match timer_idx {
-                    0 => 8,   // Timer A: bit 3
-                    1 => 16,  // Timer B: bit 4
-                    2 => 64,  // Timer C: bit 6
-                    _ => 0,
-                };
-
-                // println!("Timer {} overflow, setting IRR bit {}, i={}, 0xff0a={:02X}", timer_idx + 1, bit, self.cpu.i, self.ram[0xFF0A]);
-
-                // Set IRR bit + master interrupt flag (bit 7 = 128)
-                self.ram[0xFF09] |= bit + 128;
-
-                // Check if interrupt should trigger
-                // Interrupt fires if: interrupt flag is clear AND interrupt is enabled in mask
-                if !self.cpu.i && (self.ram[0xFF0A] & bit) != 0 {
-                    self.push_word(self.cpu.pc);
-                    self.push(self.get_flags());
-
-                    self.cpu.i = true;
-
-                    // Jump to IRQ vector at 0xFFFE/0xFFFF
-                    // let irq_lo = self.rom[0xFFFE - 0x8000] as u16;
-                    // let irq_hi = self.rom[0xFFFF - 0x8000] as u16;
-                    // self.cpu.pc = irq_lo | (irq_hi << 8);
-                let irq_lo = self.peek(0xFFFE) as u16;
-                let irq_hi = self.peek(0xFFFF) as u16;
-                // self.cpu.pc = irq_vec;
-                self.cpu.pc = irq_lo | (irq_hi << 8);
-                    // println!("Timer {} IRQ triggered, jumping to {:04X}", timer_idx + 1, self.cpu.pc);
-                }
+        // Hardware interrupts push the flags with B clear
+        self.push_word(self.cpu.pc);
+        self.push(self.get_flags());
+        self.cpu.i = true;
+
+        let irq_lo = self.peek(0xFFFE) as u16;
+        let irq_hi = self.peek(0xFFFF) as u16;
+        self.cpu.pc = irq_lo | (irq_hi << 8);
+        IRQ_ENTRY_CYCLES
+    }
+
+    // Run whole instructions until at least `cycles` CPU cycles have elapsed
+    pub fn run_cycles(&mut self, cycles: u64) {
+        let target = self.cycle_count + cycles;
+        while self.cycle_count < target {
+            self.step();
+        }
+    }
+
+    // Mono 16-bit PCM produced since the last call, at audio_sample_rate()
+    pub fn take_audio_samples(&mut self) -> Vec<i16> {
+        self.sound.take_samples()
+    }
+
+    pub fn audio_sample_rate(&self) -> u32 {
+        self.sound.sample_rate()
+    }
+
+    // Record all sound output to a WAV file until stop_recording()
+    pub fn start_recording<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.recorder = Some(WavWriter::create(path, self.sound.sample_rate())?);
+        self.recording_error = None;
+        self.sound.start_recording();
+        Ok(())
+    }
+
+    // Write the remaining samples and close the file. Returns the recorded
+    // length in seconds, or the error that stopped the recording early.
+    pub fn stop_recording(&mut self) -> std::io::Result<f32> {
+        let samples = self.sound.stop_recording();
+        if let Some(e) = self.recording_error.take() {
+            self.recorder = None;
+            return Err(e);
+        }
+        match self.recorder.take() {
+            Some(mut recorder) => {
+                recorder.write_samples(&samples)?;
+                let duration = recorder.duration();
+                recorder.finish()?;
+                Ok(duration)
+            }
+            None => Ok(0.0),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    // After a failed write the recording stays open but discards samples
+    // until stop_recording() reports the error
+    fn write_recording(&mut self) {
+        if self.recording_error.is_some() {
+            return;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            let samples = self.sound.take_recorded();
+            if let Err(e) = recorder.write_samples(&samples) {
+                self.recording_error = Some(e);
+                self.sound.stop_recording();
             }
         }
+    }
 
-        // Render raster line if needed
-        if self.clock_counter >= TICKS_PER_RASTER_LINE {
-            self.clock_counter -= TICKS_PER_RASTER_LINE;
-            self.render_raster_line();
+    // Run until the raster counter wraps to the top of the next frame
+    pub fn run_frame(&mut self) {
+        self.frame_done = false;
+        while !self.frame_done {
+            self.step();
         }
     }
 
+    // Total CPU cycles executed since power-on
+    pub fn cycles(&self) -> u64 {
+        self.cycle_count
+    }
+
     // PRG file loading
     pub fn load_prg(&mut self, prg: &crate::prg_loader::PrgFile) {
         println!("Loading PRG: ${:04X} ({} bytes)", prg.load_address, prg.data.len());
@@ -1754,6 +1523,7 @@ impl Plus4 {
     }
 
     // Setup BASIC pointers after loading a BASIC program
+    #[allow(dead_code)]
     fn setup_basic_pointers(&mut self, end_address: u16) {
         // Set BASIC end pointer
         // $9D = 157: Start of BASIC variables
@@ -1804,3 +1574,845 @@ impl Plus4 {
         // }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Place a program at $1000 and point the CPU at it
+    fn setup(program: &[u8]) -> Plus4 {
+        let mut emu = Plus4::new();
+        for (i, &byte) in program.iter().enumerate() {
+            emu.poke(0x1000 + i as u16, byte);
+        }
+        emu.cpu.pc = 0x1000;
+        emu
+    }
+
+    // Reference NMOS 6502 cycle counts without page-crossing or branch
+    // penalties. Branches are listed as not taken; 0 marks the JAM opcodes.
+    const CYCLES: [u32; 256] = [
+    //  0  1  2  3  4  5  6  7  8  9  A  B  C  D  E  F
+        7, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // 0x00
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x10
+        6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // 0x20
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x30
+        6, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // 0x40
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x50
+        6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // 0x60
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x70
+        2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0x80
+        2, 6, 0, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // 0x90
+        2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0xA0
+        2, 5, 0, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // 0xB0
+        2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xC0
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xD0
+        2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xE0
+        2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xF0
+    ];
+
+    // Opcodes that take one extra cycle when indexing crosses a page
+    const PAGE_CROSS_OPCODES: [u8; 32] = [
+        0x11, 0x19, 0x1D, 0x31, 0x39, 0x3D, 0x51, 0x59, 0x5D, 0x71, 0x79, 0x7D,
+        0xB1, 0xB3, 0xB9, 0xBB, 0xBC, 0xBD, 0xBE, 0xBF, 0xD1, 0xD9, 0xDD, 0xF1,
+        0xF9, 0xFD, 0x1C, 0x3C, 0x5C, 0x7C, 0xDC, 0xFC,
+    ];
+
+    fn is_branch(opcode: u8) -> bool {
+        (opcode & 0x1F) == 0x10
+    }
+
+    // Run a single opcode with operand bytes $01 $20: zero page $01,
+    // absolute $2001, and a ($01) pointer to $2001
+    fn cycles_for(opcode: u8, index: u8) -> u32 {
+        let mut emu = setup(&[opcode, 0x01, 0x20]);
+        emu.poke(0x01, 0x01);
+        emu.poke(0x02, 0x20);
+        emu.cpu.xr = index;
+        emu.cpu.yr = index;
+        emu.cpu.sp = 0xF0;
+        emu.execute_instruction();
+        emu.clock_ticks
+    }
+
+    #[test]
+    fn test_cycle_table() {
+        for opcode in 0..=255u8 {
+            if is_branch(opcode) || CYCLES[opcode as usize] == 0 {
+                continue;
+            }
+            assert_eq!(cycles_for(opcode, 0), CYCLES[opcode as usize],
+                       "opcode 0x{:02X}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_page_cross_penalty() {
+        for opcode in 0..=255u8 {
+            if is_branch(opcode) || CYCLES[opcode as usize] == 0 {
+                continue;
+            }
+            let penalty = if PAGE_CROSS_OPCODES.contains(&opcode) { 1 } else { 0 };
+            assert_eq!(cycles_for(opcode, 0xFF), CYCLES[opcode as usize] + penalty,
+                       "opcode 0x{:02X}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_branch_cycles() {
+        for opcode in (0x10..=0xF0u8).step_by(0x20) {
+            // Bits 7-6 select the flag, bit 5 the value that takes the branch
+            let taken_value = (opcode & 0x20) != 0;
+            let set_flag = |emu: &mut Plus4, value: bool| match opcode >> 6 {
+                0 => emu.cpu.n = value,
+                1 => emu.cpu.v = value,
+                2 => emu.cpu.c = value,
+                _ => emu.cpu.z = value,
+            };
+
+            let mut emu = setup(&[opcode, 0x10]);
+            set_flag(&mut emu, !taken_value);
+            emu.execute_instruction();
+            assert_eq!((emu.cpu.pc, emu.clock_ticks), (0x1002, 2), "opcode 0x{:02X}", opcode);
+
+            let mut emu = setup(&[opcode, 0x10]);
+            set_flag(&mut emu, taken_value);
+            emu.execute_instruction();
+            assert_eq!((emu.cpu.pc, emu.clock_ticks), (0x1012, 3), "opcode 0x{:02X}", opcode);
+
+            let mut emu = setup(&[opcode, 0xF0]);
+            set_flag(&mut emu, taken_value);
+            emu.execute_instruction();
+            assert_eq!((emu.cpu.pc, emu.clock_ticks), (0x0FF2, 4), "opcode 0x{:02X}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_tracer_records_state() {
+        use crate::trace::{ExecutionFilter, TraceFilter};
+
+        let mut emu = setup(&[0xA9, 0x42, 0xAA, 0xEA]); // LDA #$42 / TAX / NOP
+        emu.tracer = Some(Tracer::ring_buffer(8).with_filter(TraceFilter {
+            address_ranges: vec![0x1000..=0x1002],
+            execution: ExecutionFilter::RamOnly,
+            ..Default::default()
+        }));
+        for _ in 0..3 {
+            emu.step();
+        }
+
+        let entries: Vec<TraceEntry> = emu.tracer.as_ref().unwrap().entries().cloned().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].pc, 0x1000);
+        assert!(entries[0].disassembly.contains("LDA #$42"));
+        assert_eq!((entries[1].pc, entries[1].acc, entries[1].cycles), (0x1002, 0x42, 2));
+    }
+
+    #[test]
+    fn test_lax_sax() {
+        let mut emu = setup(&[0xA7, 0x10, 0x87, 0x11]); // LAX $10 / SAX $11
+        emu.poke(0x10, 0x8F);
+        emu.execute_instruction();
+        assert_eq!(emu.cpu.acc, 0x8F);
+        assert_eq!(emu.cpu.xr, 0x8F);
+        assert!(emu.cpu.n);
+        assert_eq!(emu.clock_ticks, 3);
+
+        emu.cpu.xr = 0x0F;
+        emu.execute_instruction();
+        assert_eq!(emu.peek(0x11), 0x0F);
+        assert_eq!(emu.cpu.pc, 0x1004);
+    }
+
+    #[test]
+    fn test_dcp_isc() {
+        let mut emu = setup(&[0xC7, 0x10, 0xE7, 0x11]); // DCP $10 / ISC $11
+        emu.poke(0x10, 0x43);
+        emu.poke(0x11, 0x01);
+        emu.cpu.acc = 0x42;
+        emu.execute_instruction();
+        assert_eq!(emu.peek(0x10), 0x42);
+        assert!(emu.cpu.z && emu.cpu.c);
+        assert_eq!(emu.clock_ticks, 5);
+
+        emu.execute_instruction();
+        assert_eq!(emu.peek(0x11), 0x02);
+        assert_eq!(emu.cpu.acc, 0x40);
+    }
+
+    #[test]
+    fn test_slo_rra() {
+        let mut emu = setup(&[0x0F, 0x00, 0x20, 0x6F, 0x00, 0x20]); // SLO $2000 / RRA $2000
+        emu.poke(0x2000, 0x81);
+        emu.cpu.acc = 0x10;
+        emu.execute_instruction();
+        assert_eq!(emu.peek(0x2000), 0x02);
+        assert_eq!(emu.cpu.acc, 0x12);
+        assert!(emu.cpu.c);
+
+        emu.execute_instruction();
+        assert_eq!(emu.peek(0x2000), 0x81);
+        assert_eq!(emu.cpu.acc, 0x93);
+        assert!(!emu.cpu.c);
+    }
+
+    #[test]
+    fn test_immediate_illegals() {
+        let mut emu = setup(&[0x0B, 0x80, 0x4B, 0x03, 0xCB, 0x02]); // ANC / ALR / SBX
+        emu.cpu.acc = 0xFF;
+        emu.execute_instruction();
+        assert_eq!(emu.cpu.acc, 0x80);
+        assert!(emu.cpu.c && emu.cpu.n);
+
+        emu.cpu.acc = 0x07;
+        emu.execute_instruction();
+        assert_eq!(emu.cpu.acc, 0x01);
+        assert!(emu.cpu.c);
+
+        emu.cpu.acc = 0x0F;
+        emu.cpu.xr = 0x03;
+        emu.execute_instruction();
+        assert_eq!(emu.cpu.xr, 0x01);
+        assert!(emu.cpu.c);
+    }
+
+    #[test]
+    fn test_arr_binary() {
+        let mut emu = setup(&[0x6B, 0xFF]);
+        emu.cpu.acc = 0xC0;
+        emu.cpu.c = true;
+        emu.execute_instruction();
+        assert_eq!(emu.cpu.acc, 0xE0);
+        assert!(emu.cpu.c);
+        assert!(!emu.cpu.v);
+        assert!(emu.cpu.n);
+    }
+
+    #[test]
+    fn test_multibyte_nops() {
+        let mut emu = setup(&[0x80, 0x12, 0x0C, 0x34, 0x12, 0x1C, 0x00, 0x30]);
+        emu.execute_instruction();
+        assert_eq!((emu.cpu.pc, emu.clock_ticks), (0x1002, 2));
+        emu.execute_instruction();
+        assert_eq!((emu.cpu.pc, emu.clock_ticks), (0x1005, 4));
+        emu.execute_instruction();
+        assert_eq!((emu.cpu.pc, emu.clock_ticks), (0x1008, 4));
+    }
+
+    // Program at $1000 with RAM banked in and the IRQ vector pointing to $2000
+    fn setup_irq(program: &[u8]) -> Plus4 {
+        let mut emu = setup(program);
+        emu.poke(0xFF3F, 0);
+        emu.poke(0xFFFE, 0x00);
+        emu.poke(0xFFFF, 0x20);
+        emu
+    }
+
+    #[test]
+    fn test_irq_waits_for_cli() {
+        // NOP / NOP / CLI / NOP
+        let mut emu = setup_irq(&[0xEA, 0xEA, 0x58, 0xEA]);
+        emu.cpu.i = true;
+        emu.cpu.c = true;
+        emu.poke(0xFF0A, 0x08);
+        emu.request_interrupt(0x08);
+        assert_eq!(emu.peek(0xFF09), 0x88);
+
+        // The request stays latched while interrupts are disabled
+        for _ in 0..3 {
+            emu.step();
+        }
+        assert_eq!(emu.cpu.pc, 0x1003);
+
+        let cycles = emu.cycle_count;
+        emu.step();
+        assert_eq!(emu.cpu.pc, 0x2000);
+        assert!(emu.cpu.i);
+        assert_eq!(emu.cycle_count - cycles, IRQ_ENTRY_CYCLES as u64);
+
+        // Return address and flags with B clear
+        assert_eq!(emu.cpu.sp, 0xFC);
+        assert_eq!(emu.ram[0x1FD], 0x21);
+        assert_eq!(emu.ram[0x1FE], 0x03);
+        assert_eq!(emu.ram[0x1FF], 0x10);
+    }
+
+    #[test]
+    fn test_irq_is_level_triggered() {
+        let mut emu = setup_irq(&[0xEA; 16]);
+        emu.cpu.i = false;
+
+        // A masked source is latched but does not assert the line
+        emu.request_interrupt(0x02);
+        assert_eq!(emu.peek(0xFF09), 0x02);
+        emu.step();
+        assert_eq!(emu.cpu.pc, 0x1001);
+
+        // Enabling it later raises the IRQ
+        emu.poke(0xFF0A, 0x02);
+        assert_eq!(emu.peek(0xFF09), 0x82);
+        emu.step();
+        assert_eq!(emu.cpu.pc, 0x2000);
+
+        // The line stays asserted until the source is acknowledged
+        emu.cpu.pc = 0x1000;
+        emu.cpu.i = false;
+        emu.step();
+        assert_eq!(emu.cpu.pc, 0x2000);
+
+        emu.cpu.pc = 0x1000;
+        emu.cpu.i = false;
+        emu.poke(0xFF09, 0x02);
+        assert_eq!(emu.peek(0xFF09), 0x00);
+        emu.step();
+        assert_eq!(emu.cpu.pc, 0x1001);
+    }
+
+    #[test]
+    fn test_brk_pushes_b_flag() {
+        // Restoring flags with B set (as PLP and RTI do) does not leak B
+        // into later IRQ pushes
+        let mut emu = setup_irq(&[0x00, 0x00]);
+        emu.set_flags(0xFF);
+        emu.cpu.i = false;
+        emu.execute_instruction();
+        assert_eq!(emu.cpu.pc, 0x2000);
+        assert_eq!(emu.ram[0x1FD], 0xFB);
+
+        emu.cpu.i = false;
+        emu.poke(0xFF0A, 0x02);
+        emu.request_interrupt(0x02);
+        emu.step();
+        assert_eq!(emu.ram[0x1FA], 0xEB);
+    }
+
+    #[test]
+    fn test_timer_interrupt() {
+        let mut emu = setup_irq(&[0xEA; 64]);
+        emu.cpu.i = false;
+        emu.poke(0xFF0A, 0x08);
+        emu.poke(0xFF00, 20);
+        emu.poke(0xFF01, 0);
+
+        for _ in 0..30 {
+            emu.step();
+            if emu.cpu.pc == 0x2000 {
+                break;
+            }
+        }
+        assert_eq!(emu.cpu.pc, 0x2000);
+        assert_eq!(emu.peek(0xFF09) & 0x88, 0x88);
+    }
+
+    #[test]
+    fn test_rom_banking() {
+        let mut emu = Plus4::new();
+        for slot in 0..ROM_SLOTS {
+            emu.load_rom_low(slot, &[0x10 + slot as u8; ROM_BANK_SIZE]);
+            emu.load_rom_high(slot, &[0x20 + slot as u8; ROM_BANK_SIZE]);
+        }
+        emu.load_rom_high(ROM_SLOT_CARTRIDGE2, &[]);
+
+        // The latch takes the slots from the address bits: low slot in
+        // bits 0-1, high slot in bits 2-3
+        emu.poke(0xFDD0 + (ROM_SLOT_CARTRIDGE1 << 2 | ROM_SLOT_FUNCTION) as u16, 0);
+        assert_eq!(emu.peek(0x8000), 0x11);
+        assert_eq!(emu.peek(0xBFFF), 0x11);
+        assert_eq!(emu.peek(0xC000), 0x22);
+        assert_eq!(emu.peek(0xFFFF), 0x22);
+        // KERNAL page and I/O are not banked
+        assert_eq!(emu.peek(0xFC00), 0x20);
+        assert_eq!(emu.peek(0xFCFF), 0x20);
+        emu.poke(0xFD16, 0x5A);
+        assert_eq!(emu.peek(0xFD16), 0x5A);
+
+        // Empty slot
+        emu.poke(0xFDDF, 0);
+        assert_eq!(emu.peek(0xE000), 0xFF);
+        assert_eq!(emu.peek(0xFC80), 0x20);
+
+        // RAM under the ROM
+        emu.poke(0x9000, 0x77);
+        assert_eq!(emu.peek(0x9000), 0x13);
+        emu.poke(0xFF3F, 0);
+        assert_eq!(emu.peek(0x9000), 0x77);
+        assert_eq!(emu.peek(0xFC00), 0x00);
+        emu.poke(0xFF3E, 0);
+        assert_eq!(emu.peek(0x9000), 0x13);
+
+        // Reset returns to the system ROM
+        emu.hard_reset();
+        assert_eq!(emu.peek(0x8000), 0x10);
+        assert_eq!(emu.peek(0xC000), 0x20);
+    }
+
+    #[test]
+    fn test_load_rom_file() {
+        let path = std::env::temp_dir().join(format!("plus4emu-rom-{}.bin", std::process::id()));
+        let mut image = vec![0xAA; ROM_BANK_SIZE];
+        image.extend_from_slice(&[0xBB; ROM_BANK_SIZE]);
+        std::fs::write(&path, &image).unwrap();
+
+        let mut emu = Plus4::new();
+        emu.load_rom_file(ROM_SLOT_FUNCTION, &path).unwrap();
+        emu.poke(0xFDD5, 0);
+        assert_eq!(emu.peek(0x8000), 0xAA);
+        assert_eq!(emu.peek(0xC000), 0xBB);
+
+        std::fs::write(&path, &image[..1000]).unwrap();
+        let err = emu.load_rom_file(ROM_SLOT_FUNCTION, &path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_jam_halts_until_reset() {
+        let mut emu = setup(&[0x02, 0xEA]);
+        emu.execute_instruction();
+        assert!(emu.cpu.jammed);
+        assert_eq!(emu.cpu.pc, 0x1000);
+
+        // Further steps stay on the JAM opcode and ignore interrupts
+        emu.cpu.i = false;
+        emu.poke(0xFF0A, 0xFF);
+        for _ in 0..1000 {
+            emu.step();
+        }
+        assert_eq!(emu.cpu.pc, 0x1000);
+
+        emu.hard_reset();
+        assert!(!emu.cpu.jammed);
+    }
+
+    // One line of the display window inside the frame buffer
+    fn display_row(emu: &Plus4, line: usize) -> &[u8] {
+        &emu.pixels[BORDER_TOP + line][BORDER_LEFT..BORDER_LEFT + SCREEN_WIDTH]
+    }
+
+    // Display registers for synthetic screens: video matrix at $0800,
+    // RAM charset and bitmap at $2000, 40x25 window with the default
+    // scroll offsets, cursor off screen
+    fn setup_display(ff06: u8, ff07: u8) -> Plus4 {
+        let mut emu = Plus4::new();
+        emu.ram[0xFF06] = ff06 | 0x0B;
+        emu.ram[0xFF07] = ff07 | 0x08;
+        emu.ram[0xFF0C] = 0x03;
+        emu.ram[0xFF0D] = 0xFF;
+        emu.ram[0xFF12] = 0x08;
+        emu.ram[0xFF13] = 0x20;
+        emu.ram[0xFF14] = 0x08;
+        emu.ram[0xFF15] = 0x11;
+        emu.ram[0xFF16] = 0x22;
+        emu.ram[0xFF17] = 0x33;
+        emu.ram[0xFF19] = 0x46;
+        emu
+    }
+
+    #[test]
+    fn test_multicolor_text() {
+        let mut emu = setup_display(0x10, 0x90);
+        // Column 0: multicolor character (bit 3 of the color set)
+        emu.ram[0x0C00] = 0x01;
+        emu.ram[0x0800] = 0x3A;
+        // Column 1: same character, drawn hires
+        emu.ram[0x0C01] = 0x01;
+        emu.ram[0x0801] = 0x32;
+        emu.ram[0x2008] = 0b0001_1011;
+
+        emu.render_text_line(0, BORDER_TOP);
+
+        assert_eq!(display_row(&emu, 0)[0..8], [0x11, 0x11, 0x22, 0x22, 0x33, 0x33, 0x32, 0x32]);
+        assert_eq!(display_row(&emu, 0)[8..16], [0x11, 0x11, 0x11, 0x32, 0x32, 0x11, 0x32, 0x32]);
+    }
+
+    #[test]
+    fn test_multicolor_bitmap() {
+        let mut emu = setup_display(0x30, 0x10);
+        emu.ram[0x0800] = 0x52;
+        emu.ram[0x0C00] = 0x6C;
+        emu.ram[0x2000] = 0b0001_1011;
+
+        emu.render_multicolor_bitmap_line(0, BORDER_TOP);
+
+        assert_eq!(display_row(&emu, 0)[0..8], [0x11, 0x11, 0x26, 0x26, 0x5C, 0x5C, 0x22, 0x22]);
+    }
+
+    #[test]
+    fn test_extended_color_text() {
+        let mut emu = setup_display(0x50, 0x00);
+        emu.ram[0xFF18] = 0x44;
+        // Character 1 with each of the four background selections
+        for (col, code) in [0x01, 0x41, 0x81, 0xC1].into_iter().enumerate() {
+            emu.ram[0x0C00 + col] = code;
+            emu.ram[0x0800 + col] = 0x05;
+        }
+        emu.ram[0x2008] = 0b1000_0001;
+
+        emu.render_text_line(0, BORDER_TOP);
+
+        for (col, bg) in [0x11, 0x22, 0x33, 0x44].into_iter().enumerate() {
+            let x = col * 8;
+            assert_eq!(display_row(&emu, 0)[x..x + 8], [0x05, bg, bg, bg, bg, bg, bg, 0x05]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_modes_are_black() {
+        // ECM with bitmap, ECM with multicolor text, ECM with multicolor bitmap
+        for (ff06, ff07) in [(0x78, 0x00), (0x58, 0x10), (0x78, 0x10)] {
+            let mut emu = setup_display(ff06, ff07);
+            emu.raster_line = FIRST_SCREEN_LINE as u32;
+            emu.ram[0x0C00] = 0x01;
+            emu.ram[0x0800] = 0x7F;
+            emu.ram[0x2000] = 0xFF;
+            emu.ram[0x2008] = 0xFF;
+
+            emu.render_raster_line();
+
+            assert!(display_row(&emu, 0).iter().all(|&pixel| pixel == 0), "{:02X}/{:02X}", ff06, ff07);
+        }
+    }
+
+    #[test]
+    fn test_hires_bitmap_cell_colors() {
+        let mut emu = setup_display(0x30, 0x00);
+        // Cell 0: set pixels hue 2 lum 5, clear pixels hue 6 lum 3
+        emu.ram[0x0800] = 0x53;
+        emu.ram[0x0C00] = 0x62;
+        // Cell 1: set pixels hue 7 lum 1, clear pixels hue 0 lum 0
+        emu.ram[0x0801] = 0x10;
+        emu.ram[0x0C01] = 0x07;
+        emu.ram[0x2000] = 0b1010_0000;
+        emu.ram[0x2008] = 0b0000_0001;
+
+        emu.render_bitmap_line(0, BORDER_TOP);
+
+        assert_eq!(display_row(&emu, 0)[0..4], [0x52, 0x36, 0x52, 0x36]);
+        assert_eq!(display_row(&emu, 0)[8..16], [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x17]);
+    }
+
+    #[test]
+    fn test_border_fills_frame() {
+        let mut emu = setup_display(0x10, 0x00);
+        emu.ram[0x0C00] = 0x01;
+        emu.ram[0x0800] = 0x05;
+        emu.ram[0x2008] = 0xFF;
+
+        // Every raster line in the visible frame paints its border
+        for raster_line in 0..RASTER_LINES {
+            emu.raster_line = raster_line;
+            emu.render_raster_line();
+        }
+
+        // Top and bottom border lines are border color from edge to edge
+        assert!(emu.pixels[0].iter().all(|&pixel| pixel == 0x46));
+        assert!(emu.pixels[FRAME_HEIGHT - 1].iter().all(|&pixel| pixel == 0x46));
+
+        // Display lines keep the side borders around the display window
+        let row = &emu.pixels[BORDER_TOP];
+        assert!(row[..BORDER_LEFT].iter().all(|&pixel| pixel == 0x46));
+        assert!(row[BORDER_LEFT + SCREEN_WIDTH..].iter().all(|&pixel| pixel == 0x46));
+        assert_eq!(row[BORDER_LEFT..BORDER_LEFT + 8], [0x05; 8]);
+        assert_eq!(row[BORDER_LEFT + 8], 0x11);
+
+        // Blanking the screen leaves only the border
+        emu.ram[0xFF06] = 0x00;
+        emu.raster_line = FIRST_SCREEN_LINE as u32;
+        emu.render_raster_line();
+        assert!(emu.pixels[BORDER_TOP].iter().all(|&pixel| pixel == 0x46));
+    }
+
+    // Render the raster lines of display window line 0 to 7 with the given
+    // scroll registers, content line N filled with character row N % 8
+    fn render_scrolled(ff06: u8, ff07: u8) -> Plus4 {
+        let mut emu = setup_display(0x00, 0x00);
+        emu.ram[0xFF06] = ff06;
+        emu.ram[0xFF07] = ff07;
+        for col in 0..40 {
+            emu.ram[0x0C00 + col] = 0x01;
+            emu.ram[0x0800 + col] = 0x05;
+        }
+        emu.ram[0x2008] = 0b1000_0000;
+        emu.ram[0x2009] = 0b0100_0000;
+
+        for raster_line in FIRST_SCREEN_LINE as u32..FIRST_SCREEN_LINE as u32 + 8 {
+            emu.raster_line = raster_line;
+            emu.render_raster_line();
+        }
+        emu
+    }
+
+    #[test]
+    fn test_vertical_scroll() {
+        // YSCROLL 3 is the unscrolled default
+        let emu = render_scrolled(0x1B, 0x08);
+        assert_eq!(display_row(&emu, 0)[0..2], [0x05, 0x11]);
+        assert_eq!(display_row(&emu, 1)[0..2], [0x11, 0x05]);
+
+        // YSCROLL 5 moves the content down two lines over the background
+        let emu = render_scrolled(0x1D, 0x08);
+        assert_eq!(display_row(&emu, 1)[0..2], [0x11, 0x11]);
+        assert_eq!(display_row(&emu, 2)[0..2], [0x05, 0x11]);
+        assert_eq!(display_row(&emu, 3)[0..2], [0x11, 0x05]);
+
+        // YSCROLL 2 moves it up, the first content line is hidden
+        let emu = render_scrolled(0x1A, 0x08);
+        assert_eq!(display_row(&emu, 0)[0..2], [0x11, 0x05]);
+    }
+
+    #[test]
+    fn test_horizontal_scroll() {
+        let emu = render_scrolled(0x1B, 0x0B);
+        assert_eq!(display_row(&emu, 0)[0..5], [0x11, 0x11, 0x11, 0x05, 0x11]);
+        assert_eq!(display_row(&emu, 0)[8..12], [0x11, 0x11, 0x11, 0x05]);
+    }
+
+    #[test]
+    fn test_narrow_window() {
+        // 38 columns: the border covers 8 pixels on each side
+        let emu = render_scrolled(0x1B, 0x00);
+        let row = display_row(&emu, 0);
+        assert_eq!(row[0..8], [0x46; 8]);
+        assert_eq!(row[8], 0x05);
+        assert_eq!(row[SCREEN_WIDTH - 8..], [0x46; 8]);
+
+        // 24 rows: the border covers the first 4 lines
+        let emu = render_scrolled(0x13, 0x08);
+        assert!(display_row(&emu, 3).iter().all(|&pixel| pixel == 0x46));
+        assert_eq!(display_row(&emu, 4)[0..2], [0x11, 0x11]);
+    }
+
+    #[test]
+    fn test_raster_polling() {
+        // loop: LDA $FF1D / CMP #$64 / BNE loop
+        let mut emu = setup(&[0xAD, 0x1D, 0xFF, 0xC9, 0x64, 0xD0, 0xF9]);
+        for _ in 0..100_000 {
+            if emu.cpu.pc == 0x1007 {
+                break;
+            }
+            emu.step();
+        }
+        assert_eq!(emu.cpu.pc, 0x1007);
+        assert_eq!(emu.raster_line(), 0x64);
+        assert_eq!(emu.peek(0xFF1C), 0xFE);
+    }
+
+    #[test]
+    fn test_ted_counter_registers() {
+        let mut emu = setup_display(0x10, 0x00);
+
+        // Writing the raster counter moves the beam
+        emu.poke(0xFF1C, 0x01);
+        emu.poke(0xFF1D, 0x05);
+        assert_eq!(emu.raster_line(), 0x105);
+        assert_eq!(emu.peek(0xFF1C), 0xFF);
+        assert_eq!(emu.peek(0xFF1D), 0x05);
+
+        // Line 21 with YSCROLL 3 is pixel row 2 of character row 2
+        emu.poke(0xFF1C, 0x00);
+        emu.poke(0xFF1D, 21);
+        assert_eq!(emu.peek(0xFF1A), 0xFC);
+        assert_eq!(emu.peek(0xFF1B), 80);
+        assert_eq!(emu.peek(0xFF1F) & 0x87, 0x82);
+
+        // The flash counter advances once per frame
+        emu.poke(0xFF1F, 0x05 << 3);
+        emu.poke(0xFF1D, (RASTER_LINES - 1) as u8);
+        emu.poke(0xFF1C, ((RASTER_LINES - 1) >> 8) as u8);
+        emu.render_raster_line();
+        assert_eq!(emu.raster_line(), 0);
+        assert_eq!((emu.peek(0xFF1F) >> 3) & 0x0F, 0x06);
+
+        // The flash phase only changes when the counter wraps, every 16 frames
+        let flash_phase = emu.flash_phase;
+        emu.poke(0xFF1F, 0x0F << 3);
+        emu.poke(0xFF1D, (RASTER_LINES - 1) as u8);
+        emu.poke(0xFF1C, ((RASTER_LINES - 1) >> 8) as u8);
+        emu.render_raster_line();
+        assert_eq!((emu.peek(0xFF1F) >> 3) & 0x0F, 0x00);
+        assert_eq!(emu.flash_phase, !flash_phase);
+        for _ in 0..15 {
+            emu.run_frame();
+            assert_eq!(emu.flash_phase, !flash_phase);
+        }
+        emu.run_frame();
+        assert_eq!(emu.flash_phase, flash_phase);
+
+        // Horizontal position follows the cycles spent on the line
+        emu.clock_counter = 57;
+        assert_eq!(emu.peek(0xFF1E), 114);
+    }
+
+    // CPU cycles of one full frame running a JMP loop
+    fn cycles_per_frame(ff06: u8, ff13: u8) -> u64 {
+        let mut emu = setup(&[0x4C, 0x00, 0x10]);
+        emu.ram[0xFF06] = ff06;
+        emu.ram[0xFF13] = ff13;
+        emu.run_frame();
+        let start = emu.cycles();
+        emu.run_frame();
+        emu.cycles() - start
+    }
+
+    #[test]
+    fn test_frame_timing() {
+        let lines = RASTER_LINES as u64;
+        let line = TICKS_PER_RASTER_LINE as u64;
+        let fetch = (FETCH_WINDOW_END - FETCH_WINDOW_START) as u64;
+        let display_lines = SCREEN_HEIGHT as u64;
+        let badlines = display_lines / 8;
+
+        // Blanked screen: double clock all the time
+        assert!(cycles_per_frame(0x0B, 0x00).abs_diff(lines * line) <= 3);
+
+        // Forced single clock
+        assert!(cycles_per_frame(0x0B, 0x02).abs_diff(lines * line / 2) <= 3);
+
+        // Screen on: single clock during display fetches, CPU halted during
+        // the attribute fetches of badlines
+        let expected = (lines - display_lines) * line
+            + (display_lines - badlines) * (line - fetch / 2)
+            + badlines * (line - fetch);
+        assert!(cycles_per_frame(0x1B, 0x00).abs_diff(expected) <= 3);
+
+        // Writing the flash counter does not end the frame early
+        // loop: LDA #$00 / STA $FF1F / JMP loop
+        let mut emu = setup(&[0xA9, 0x00, 0x8D, 0x1F, 0xFF, 0x4C, 0x00, 0x10]);
+        emu.ram[0xFF06] = 0x0B;
+        emu.run_frame();
+        let start = emu.cycles();
+        emu.run_frame();
+        assert!((emu.cycles() - start).abs_diff(lines * line) <= 6);
+    }
+
+    #[test]
+    fn test_ntsc_frame_timing() {
+        let mut emu = Plus4::with_model(MachineModel::Plus4Ntsc);
+        emu.load_ram(0x1000, &[0x4C, 0x00, 0x10]);
+        emu.cpu.pc = 0x1000;
+        emu.ram[0xFF06] = 0x0B;
+        emu.run_frame();
+        let start = emu.cycles();
+        emu.run_frame();
+        let lines = crate::machine::NTSC_RASTER_LINES as u64;
+        assert!((emu.cycles() - start).abs_diff(lines * TICKS_PER_RASTER_LINE as u64) <= 3);
+
+        // Every line of the cropped frame is drawn, the rest stays unused
+        emu.pixels = [[0; FRAME_WIDTH]; FRAME_HEIGHT];
+        emu.ram[0xFF19] = 0x46;
+        emu.run_frame();
+        assert_eq!(emu.frame_height(), 262);
+        assert!(emu.pixels[..262].iter().all(|row| row.iter().all(|&pixel| pixel == 0x46)));
+        assert!(emu.pixels[262..].iter().all(|row| row.iter().all(|&pixel| pixel == 0)));
+    }
+
+    #[test]
+    fn test_16k_ram_is_mirrored() {
+        let mut emu = Plus4::with_model(MachineModel::C16);
+        emu.poke(0x1234, 0x56);
+        assert_eq!(emu.peek(0x5234), 0x56);
+        emu.poke(0x7234, 0x78);
+        assert_eq!(emu.peek(0x3234), 0x78);
+        emu.poke(0x7FFF, 0x9A);
+        assert_eq!(emu.peek(0x3FFF), 0x9A);
+
+        // Above 0x8000 with the ROM switched off; I/O is not mirrored
+        emu.poke(0xFF3F, 0);
+        assert_eq!(emu.peek(0xD234), 0x56);
+        emu.poke(0xFF15, 0x71);
+        assert_eq!(emu.peek(0x3F15), 0x00);
+
+        let mut emu = Plus4::new();
+        emu.poke(0x1234, 0x56);
+        assert_eq!(emu.peek(0x5234), 0x00);
+    }
+
+    #[test]
+    fn test_hannes_ram_banks() {
+        let mut emu = Plus4::new();
+        emu.set_ram_expansion(RamExpansion::Hannes256K);
+        emu.poke(0x0200, 0x11);
+        emu.poke(0x2000, 0x22);
+
+        // CPU bank 1, TED bank 0
+        emu.poke(0xFD16, 0x01);
+        assert_eq!(emu.peek(0xFD16), 0x01);
+        assert_eq!(emu.peek(0x0200), 0x11);
+        assert_eq!(emu.peek(0x2000), 0x00);
+        emu.poke(0x2000, 0x33);
+        assert_eq!(emu.ted_ram(0x2000), 0x22);
+
+        // TED follows bits 2-3
+        emu.poke(0xFD16, 0x04);
+        assert_eq!(emu.peek(0x2000), 0x22);
+        assert_eq!(emu.ted_ram(0x2000), 0x33);
+
+        // Reset selects bank 0 and keeps the expansion RAM
+        emu.poke(0xFD16, 0x01);
+        emu.hard_reset();
+        assert_eq!(emu.peek(0x2000), 0x22);
+        emu.poke(0xFD16, 0x01);
+        assert_eq!(emu.peek(0x2000), 0x33);
+    }
+
+    #[test]
+    fn test_mid_line_border_split() {
+        // LDA #$22 / STA $FF19 on a top border line, screen blanked
+        let mut emu = setup(&[0xA9, 0x22, 0x8D, 0x19, 0xFF]);
+        emu.ram[0xFF19] = 0x11;
+        emu.raster_line = 0;
+        emu.clock_counter = 40;
+        // Raster line 0 is in the top border, after the wrap from the previous frame
+        let frame_line = BORDER_TOP - FIRST_SCREEN_LINE;
+
+        emu.step();
+        emu.step();
+        assert_eq!(emu.clock_counter, 46);
+        emu.render_raster_line();
+
+        // The store lands on its last cycle, 3 cycles into the instruction
+        let split = Plus4::beam_x(45);
+        let row = &emu.pixels[frame_line];
+        assert_eq!(split, 148);
+        assert!(row[..split].iter().all(|&pixel| pixel == 0x11));
+        assert!(row[split..].iter().all(|&pixel| pixel == 0x22));
+    }
+
+    #[test]
+    fn test_flash_reverse_and_cursor() {
+        // 128 character mode with hardware reverse
+        let mut emu = setup_display(0x10, 0x00);
+        // Column 0: flashing character, column 1: reversed character,
+        // column 2: plain character under the cursor
+        emu.ram[0x0C00] = 0x01;
+        emu.ram[0x0800] = 0x85;
+        emu.ram[0x0C01] = 0x81;
+        emu.ram[0x0801] = 0x05;
+        emu.ram[0x0C02] = 0x01;
+        emu.ram[0x0802] = 0x05;
+        emu.ram[0xFF0C] = 0x00;
+        emu.ram[0xFF0D] = 0x02;
+        emu.ram[0x2008] = 0b1111_0000;
+        let on = [0x05, 0x05, 0x05, 0x05, 0x11, 0x11, 0x11, 0x11];
+        let off = [0x11; 8];
+        let inverted = [0x11, 0x11, 0x11, 0x11, 0x05, 0x05, 0x05, 0x05];
+
+        emu.flash_phase = true;
+        emu.render_text_line(0, BORDER_TOP);
+        assert_eq!(display_row(&emu, 0)[0..8], on);
+        assert_eq!(display_row(&emu, 0)[8..16], inverted);
+        assert_eq!(display_row(&emu, 0)[16..24], inverted);
+
+        emu.flash_phase = false;
+        emu.render_text_line(0, BORDER_TOP);
+        assert_eq!(display_row(&emu, 0)[0..8], off);
+        assert_eq!(display_row(&emu, 0)[8..16], inverted);
+        assert_eq!(display_row(&emu, 0)[16..24], on);
+
+        // 256 character mode: code $81 is its own character, not reversed
+        emu.ram[0xFF07] |= 0x80;
+        emu.ram[0x2408] = 0b0000_0011;
+        emu.render_text_line(0, BORDER_TOP);
+        assert_eq!(display_row(&emu, 0)[8..16], [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x05, 0x05]);
+    }
+}
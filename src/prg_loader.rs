@@ -1,11 +1,11 @@
-/// PRG file loader for Plus/4
-/// PRG format: 2 bytes load address (little endian) + program data
-/// Copyright (C) 2025
-///
-/// This program is free software; you can redistribute it and/or
-/// modify it under the terms of the GNU General Public License
-/// as published by the Free Software Foundation; either version 2
-/// of the License, or (at your option) any later version.
+//! PRG file loader for Plus/4
+//! PRG format: 2 bytes load address (little endian) + program data
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
 
 use std::fs::File;
 use std::io::{Read, Result};
@@ -0,0 +1,320 @@
+//! System ROM sets: BASIC, KERNAL and the 3-plus-1 function ROM
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+//!
+//! Each ROM is a 16K image. Images not given on the command line or in the
+//! config file fall back to the built-in ROMs. Known revisions are
+//! identified by their CRC-32.
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::plus4::ROM_BANK_SIZE;
+
+static BUILTIN_SYSTEM: &[u8] = include_bytes!("../roms/rom.bin");
+static BUILTIN_FUNCTION: &[u8] = include_bytes!("../roms/3plus1.bin");
+
+// CRC-32 of known ROM images. The built-in KERNAL and 3-plus-1 HI images
+// match none of them and are reported as unknown.
+const KNOWN_ROMS: &[(u32, RomKind, &str)] = &[
+    (0x74EA_AE87, RomKind::Basic, "BASIC 3.5 (318006-01)"),
+    (0x77BA_B934, RomKind::Kernal, "KERNAL PAL (318004-03)"),
+    (0xBE54_ED79, RomKind::Kernal, "KERNAL PAL (318004-04)"),
+    (0x71C0_7BD4, RomKind::Kernal, "KERNAL PAL (318004-05)"),
+    (0x799A_633D, RomKind::Kernal, "KERNAL NTSC (318005-04)"),
+    (0x7029_5038, RomKind::Kernal, "KERNAL NTSC (318005-05)"),
+    (0x4FD1_D8CB, RomKind::FunctionLow, "3-plus-1 LO (317053-01)"),
+    (0x109D_E2FC, RomKind::FunctionHigh, "3-plus-1 HI (317054-01)"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomKind {
+    Basic,
+    Kernal,
+    FunctionLow,
+    FunctionHigh,
+}
+
+impl RomKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RomKind::Basic => "BASIC",
+            RomKind::Kernal => "KERNAL",
+            RomKind::FunctionLow => "function LO",
+            RomKind::FunctionHigh => "function HI",
+        }
+    }
+}
+
+/// ROM image files selected by the user; `None` uses the built-in image
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RomPaths {
+    pub basic: Option<PathBuf>,
+    pub kernal: Option<PathBuf>,
+    pub function_low: Option<PathBuf>,
+    pub function_high: Option<PathBuf>,
+    /// C16/C116 layout: leave the function ROM slot empty
+    pub no_function_rom: bool,
+}
+
+impl RomPaths {
+    /// Read `key = value` lines from a config file. Relative paths are
+    /// taken relative to the config file.
+    pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        Self::parse_config(&text, base)
+    }
+
+    /// Parse config text. Recognized keys: basic, kernal, function_lo,
+    /// function_hi (file paths) and function_rom = none. Lines starting
+    /// with # are comments.
+    pub fn parse_config(text: &str, base: &Path) -> Result<Self> {
+        let mut paths = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(config_error(number, "expected key = value"));
+            };
+            let value = value.trim();
+            let file = || Some(base.join(value));
+            match key.trim() {
+                "basic" => paths.basic = file(),
+                "kernal" => paths.kernal = file(),
+                "function_lo" => paths.function_low = file(),
+                "function_hi" => paths.function_high = file(),
+                "function_rom" if value == "none" => paths.no_function_rom = true,
+                other => return Err(config_error(number, &format!("unknown setting '{}'", other))),
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Settings of `other` take precedence over these
+    pub fn merge(self, other: RomPaths) -> RomPaths {
+        RomPaths {
+            basic: other.basic.or(self.basic),
+            kernal: other.kernal.or(self.kernal),
+            function_low: other.function_low.or(self.function_low),
+            function_high: other.function_high.or(self.function_high),
+            no_function_rom: self.no_function_rom || other.no_function_rom,
+        }
+    }
+}
+
+/// The system ROM images, 16K each
+#[derive(Debug, Clone)]
+pub struct RomSet {
+    pub basic: Vec<u8>,
+    pub kernal: Vec<u8>,
+    /// Empty on machines without the 3-plus-1 software (C16, C116)
+    pub function_low: Vec<u8>,
+    pub function_high: Vec<u8>,
+}
+
+impl Default for RomSet {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl RomSet {
+    /// The ROMs compiled into the emulator
+    pub fn builtin() -> Self {
+        let (basic, kernal) = BUILTIN_SYSTEM.split_at(ROM_BANK_SIZE);
+        let (function_low, function_high) = BUILTIN_FUNCTION.split_at(ROM_BANK_SIZE);
+        Self {
+            basic: basic.to_vec(),
+            kernal: kernal.to_vec(),
+            function_low: function_low.to_vec(),
+            function_high: function_high.to_vec(),
+        }
+    }
+
+    /// Load the selected images, using the built-in ROMs for the others
+    pub fn load(paths: &RomPaths) -> Result<Self> {
+        let mut set = Self::builtin();
+        if let Some(path) = &paths.basic {
+            set.basic = read_rom(RomKind::Basic, path)?;
+        }
+        if let Some(path) = &paths.kernal {
+            set.kernal = read_rom(RomKind::Kernal, path)?;
+        }
+        if paths.no_function_rom {
+            set.function_low.clear();
+            set.function_high.clear();
+        }
+        if let Some(path) = &paths.function_low {
+            set.function_low = read_rom(RomKind::FunctionLow, path)?;
+        }
+        if let Some(path) = &paths.function_high {
+            set.function_high = read_rom(RomKind::FunctionHigh, path)?;
+        }
+        Ok(set)
+    }
+
+    pub fn has_function_rom(&self) -> bool {
+        !self.function_low.is_empty() || !self.function_high.is_empty()
+    }
+
+    /// Whether the KERNAL is a known NTSC revision. The built-in KERNAL is a
+    /// PAL one and programs the TED for PAL even on an NTSC machine.
+    pub fn has_ntsc_kernal(&self) -> bool {
+        identify_rom(&self.kernal).is_some_and(|(_, name)| name.starts_with("KERNAL NTSC"))
+    }
+
+    /// Name of each ROM, or its CRC-32 if the revision is unknown
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![
+            describe_rom(RomKind::Basic, &self.basic),
+            describe_rom(RomKind::Kernal, &self.kernal),
+        ];
+        if self.has_function_rom() {
+            lines.push(describe_rom(RomKind::FunctionLow, &self.function_low));
+            lines.push(describe_rom(RomKind::FunctionHigh, &self.function_high));
+        } else {
+            lines.push("no function ROM (C16/C116 layout)".to_string());
+        }
+        lines
+    }
+}
+
+/// Known revision of a ROM image
+pub fn identify_rom(data: &[u8]) -> Option<(RomKind, &'static str)> {
+    let crc = crc32(data);
+    KNOWN_ROMS
+        .iter()
+        .find(|(known, _, _)| *known == crc)
+        .map(|&(_, kind, name)| (kind, name))
+}
+
+fn describe_rom(kind: RomKind, data: &[u8]) -> String {
+    if data.is_empty() {
+        return format!("{}: empty", kind.name());
+    }
+    match identify_rom(data) {
+        Some((found, name)) if found == kind => name.to_string(),
+        Some((_, name)) => format!("{}: {}, which is not a {} ROM", kind.name(), name, kind.name()),
+        None => format!("{}: unknown revision, CRC-32 {:08X}", kind.name(), crc32(data)),
+    }
+}
+
+fn read_rom(kind: RomKind, path: &Path) -> Result<Vec<u8>> {
+    let data = std::fs::read(path).map_err(|e| {
+        Error::new(e.kind(), format!("{} ROM {}: {}", kind.name(), path.display(), e))
+    })?;
+    if data.len() != ROM_BANK_SIZE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{} ROM {} must be {} bytes, got {}",
+                kind.name(),
+                path.display(),
+                ROM_BANK_SIZE,
+                data.len()
+            ),
+        ));
+    }
+    Ok(data)
+}
+
+fn config_error(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("line {}: {}", line + 1, message))
+}
+
+/// CRC-32 (IEEE), as printed by zip and most ROM databases
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_builtin_roms_are_identified() {
+        let set = RomSet::builtin();
+        assert_eq!(identify_rom(&set.basic).unwrap().0, RomKind::Basic);
+        assert_eq!(identify_rom(&set.function_low).unwrap().0, RomKind::FunctionLow);
+        // Not released revisions; reported with their CRC
+        assert!(identify_rom(&set.kernal).is_none());
+        assert!(identify_rom(&set.function_high).is_none());
+        let description = set.describe();
+        assert_eq!(description[1], "KERNAL: unknown revision, CRC-32 64DCED93");
+        assert_eq!(description[3], "function HI: unknown revision, CRC-32 AAB61387");
+        assert!(!set.has_ntsc_kernal());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let text = "# ROM set\nkernal = kernal.318005-05.bin\n\nfunction_rom = none\n";
+        let paths = RomPaths::parse_config(text, Path::new("/roms")).unwrap();
+        assert_eq!(paths.kernal, Some(PathBuf::from("/roms/kernal.318005-05.bin")));
+        assert!(paths.basic.is_none());
+        assert!(paths.no_function_rom);
+
+        let err = RomPaths::parse_config("kernel = x.bin", Path::new("")).unwrap_err();
+        assert!(err.to_string().contains("line 1"), "{}", err);
+        assert!(RomPaths::parse_config("kernal", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_command_line_overrides_config() {
+        let config = RomPaths {
+            basic: Some(PathBuf::from("a.bin")),
+            kernal: Some(PathBuf::from("b.bin")),
+            ..Default::default()
+        };
+        let args = RomPaths { kernal: Some(PathBuf::from("c.bin")), ..Default::default() };
+        let paths = config.merge(args);
+        assert_eq!(paths.basic, Some(PathBuf::from("a.bin")));
+        assert_eq!(paths.kernal, Some(PathBuf::from("c.bin")));
+    }
+
+    #[test]
+    fn test_load_rom_set() {
+        let dir = std::env::temp_dir();
+        let kernal = dir.join(format!("plus4emu-kernal-{}.bin", std::process::id()));
+        let short = dir.join(format!("plus4emu-short-{}.bin", std::process::id()));
+        std::fs::write(&kernal, vec![0x55; ROM_BANK_SIZE]).unwrap();
+        std::fs::write(&short, vec![0x55; 8192]).unwrap();
+
+        let paths = RomPaths { kernal: Some(kernal.clone()), no_function_rom: true, ..Default::default() };
+        let set = RomSet::load(&paths).unwrap();
+        assert_eq!(set.kernal, vec![0x55; ROM_BANK_SIZE]);
+        assert_eq!(set.basic, RomSet::builtin().basic);
+        assert!(!set.has_function_rom());
+        let description = set.describe();
+        assert!(description[1].contains("unknown revision"), "{:?}", description);
+        assert!(description[2].contains("C16"), "{:?}", description);
+
+        let paths = RomPaths { basic: Some(short.clone()), ..Default::default() };
+        let err = RomSet::load(&paths).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("must be 16384 bytes, got 8192"), "{}", err);
+
+        std::fs::remove_file(&kernal).unwrap();
+        std::fs::remove_file(&short).unwrap();
+    }
+}
@@ -1,32 +1,73 @@
-/// Screen rendering with macroquad
-/// Copyright (C) 2009 Florian Wolff (florian@donuz.de)
-/// Rust port 2025
-///
-/// This program is free software; you can redistribute it and/or
-/// modify it under the terms of the GNU General Public License
-/// as published by the Free Software Foundation; either version 2
-/// of the License, or (at your option) any later version.
+//! Screen rendering with macroquad
+//! Copyright (C) 2009 Florian Wolff (florian@donuz.de)
+//! Rust port 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
 
 use macroquad::prelude::*;
-use crate::plus4::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::plus4::{
+    BORDER_LEFT, BORDER_TOP, FRAME_HEIGHT, FRAME_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+// Part of the emulated frame that is shown in the window
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    // Only the 320x200 display window
+    Cropped,
+    // The whole visible frame including the border
+    FullFrame,
+}
+
+impl View {
+    pub fn width(self) -> usize {
+        match self {
+            View::Cropped => SCREEN_WIDTH,
+            View::FullFrame => FRAME_WIDTH,
+        }
+    }
+
+    // `frame_height` is the number of frame lines the machine model draws
+    pub fn height(self, frame_height: usize) -> usize {
+        match self {
+            View::Cropped => SCREEN_HEIGHT,
+            View::FullFrame => frame_height,
+        }
+    }
+
+    // Top left corner of the view in the frame buffer
+    fn origin(self) -> (usize, usize) {
+        match self {
+            View::Cropped => (BORDER_LEFT, BORDER_TOP),
+            View::FullFrame => (0, 0),
+        }
+    }
+}
 
 pub struct Screen {
     texture: Texture2D,
     palette: [Color; 128],
+    view: View,
+    frame_height: usize,
 }
 
 impl Screen {
-    pub fn new() -> Self {
+    pub fn new(view: View, frame_height: usize) -> Self {
+        let (width, height) = (view.width(), view.height(frame_height));
         let texture = Texture2D::from_rgba8(
-            SCREEN_WIDTH as u16,
-            SCREEN_HEIGHT as u16,
-            &vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT * 4],
+            width as u16,
+            height as u16,
+            &vec![0u8; width * height * 4],
         );
         texture.set_filter(FilterMode::Nearest);
 
         let mut screen = Self {
             texture,
             palette: [BLACK; 128],
+            view,
+            frame_height,
         };
         screen.load_palette();
         screen
@@ -171,14 +212,16 @@ impl Screen {
         self.palette[0x7F] = Color::from_rgba(255, 255, 255, 255);
     }
 
-    pub fn update(&mut self, pixels: &[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]) {
-        let mut rgba_data = vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT * 4];
+    pub fn update(&mut self, pixels: &[[u8; FRAME_WIDTH]; FRAME_HEIGHT]) {
+        let (width, height) = (self.view.width(), self.view.height(self.frame_height));
+        let (left, top) = self.view.origin();
+        let mut rgba_data = vec![0u8; width * height * 4];
 
-        for y in 0..SCREEN_HEIGHT {
-            for x in 0..SCREEN_WIDTH {
-                let color_idx = pixels[y][x] as usize % 128;
+        for (y, row) in pixels[top..top + height].iter().enumerate() {
+            for (x, &pixel) in row[left..left + width].iter().enumerate() {
+                let color_idx = pixel as usize % 128;
                 let color = self.palette[color_idx];
-                let idx = (y * SCREEN_WIDTH + x) * 4;
+                let idx = (y * width + x) * 4;
                 rgba_data[idx] = (color.r * 255.0) as u8;
                 rgba_data[idx + 1] = (color.g * 255.0) as u8;
                 rgba_data[idx + 2] = (color.b * 255.0) as u8;
@@ -188,15 +231,15 @@ impl Screen {
 
         let image = Image {
             bytes: rgba_data,
-            width: SCREEN_WIDTH as u16,
-            height: SCREEN_HEIGHT as u16,
+            width: width as u16,
+            height: height as u16,
         };
         self.texture.update(&image);
     }
 
     pub fn draw(&self, scale: f32) {
-        let width = SCREEN_WIDTH as f32 * scale;
-        let height = SCREEN_HEIGHT as f32 * scale;
+        let width = self.view.width() as f32 * scale;
+        let height = self.view.height(self.frame_height) as f32 * scale;
 
         draw_texture_ex(
             &self.texture,
@@ -0,0 +1,267 @@
+//! TED sound generator
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+/// Default output sample rate in Hz
+pub const SAMPLE_RATE: u32 = 44100;
+
+// The oscillators advance once every 8 double-clock cycles
+const OSCILLATOR_PRESCALER: u32 = 8;
+
+// Output level of one voice at volume 1; two voices at volume 8 stay
+// below i16::MAX
+const VOLUME_STEP: i32 = 2000;
+
+// Samples kept when nobody drains the buffer (one second)
+const MAX_BUFFERED_SECONDS: u32 = 1;
+
+/// Two TED voices: square waves on both, or noise on voice 2. Clocked once
+/// per double-clock cycle and resampled to mono 16-bit PCM.
+pub struct TedSound {
+    // 10-bit oscillator reload values from 0xFF0E/0xFF12 and 0xFF0F/0xFF10
+    frequency: [u16; 2],
+    counter: [u16; 2],
+    output: [bool; 2],
+    // 8-bit noise shift register, clocked by voice 2
+    noise: u8,
+    // 0xFF11: bits 0-3 volume, bit 4 voice 1, bit 5 voice 2 square,
+    // bit 6 voice 2 noise, bit 7 D/A mode
+    control: u8,
+    prescaler: u32,
+
+    clock_frequency: u32,
+    sample_rate: u32,
+    sample_phase: u32,
+    level_sum: i32,
+    level_count: i32,
+    samples: Vec<i16>,
+    // Second copy of the samples while recording, independent of take_samples()
+    recorded: Option<Vec<i16>>,
+}
+
+impl TedSound {
+    pub fn new(clock_frequency: u32, sample_rate: u32) -> Self {
+        Self {
+            frequency: [0; 2],
+            counter: [0; 2],
+            output: [false; 2],
+            noise: 0xFF,
+            control: 0,
+            prescaler: 0,
+            clock_frequency,
+            sample_rate,
+            sample_phase: 0,
+            level_sum: 0,
+            level_count: 0,
+            samples: Vec::new(),
+            recorded: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.frequency = [0; 2];
+        self.counter = [0; 2];
+        self.output = [false; 2];
+        self.noise = 0xFF;
+        self.control = 0;
+        self.prescaler = 0;
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Handle a CPU write to one of the sound registers 0xFF0E-0xFF12
+    pub fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xFF0E => self.frequency[0] = (self.frequency[0] & 0x300) | value as u16,
+            0xFF0F => self.frequency[1] = (self.frequency[1] & 0x300) | value as u16,
+            0xFF10 => self.frequency[1] = (self.frequency[1] & 0xFF) | ((value as u16 & 3) << 8),
+            0xFF11 => self.control = value,
+            // The other bits of 0xFF12 select the bitmap and character ROM
+            0xFF12 => self.frequency[0] = (self.frequency[0] & 0xFF) | ((value as u16 & 3) << 8),
+            _ => {}
+        }
+    }
+
+    /// Advance by one double-clock cycle
+    pub fn tick(&mut self) {
+        self.prescaler += 1;
+        if self.prescaler >= OSCILLATOR_PRESCALER {
+            self.prescaler = 0;
+            self.clock_oscillators();
+        }
+
+        self.level_sum += self.level();
+        self.level_count += 1;
+
+        self.sample_phase += self.sample_rate;
+        if self.sample_phase >= self.clock_frequency {
+            self.sample_phase -= self.clock_frequency;
+            self.push_sample((self.level_sum / self.level_count) as i16);
+            self.level_sum = 0;
+            self.level_count = 0;
+        }
+    }
+
+    /// Remove and return the samples produced so far
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        std::mem::take(&mut self.samples)
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recorded = Some(Vec::new());
+    }
+
+    pub fn stop_recording(&mut self) -> Vec<i16> {
+        self.recorded.take().unwrap_or_default()
+    }
+
+    /// Remove and return the samples recorded so far
+    pub fn take_recorded(&mut self) -> Vec<i16> {
+        self.recorded.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // Each oscillator counts up to 1024, then reloads its frequency value
+    // and flips its output
+    fn clock_oscillators(&mut self) {
+        for voice in 0..2 {
+            self.counter[voice] += 1;
+            if self.counter[voice] >= 1024 {
+                self.counter[voice] = self.frequency[voice];
+                self.output[voice] = !self.output[voice];
+                if voice == 1 {
+                    self.clock_noise();
+                }
+            }
+        }
+    }
+
+    fn clock_noise(&mut self) {
+        let n = self.noise;
+        let feedback = ((n >> 7) ^ (n >> 5) ^ (n >> 4) ^ (n >> 3)) & 1;
+        self.noise = (n << 1) | feedback;
+    }
+
+    // Current output level of both voices
+    fn level(&self) -> i32 {
+        let volume = (self.control & 0x0F).min(8) as i32 * VOLUME_STEP;
+        // In D/A mode the enabled voices output a constant high level
+        let da_mode = (self.control & 0x80) != 0;
+
+        let mut level = 0;
+        if (self.control & 0x10) != 0 && (da_mode || self.output[0]) {
+            level += volume;
+        }
+        if (self.control & 0x20) != 0 {
+            if da_mode || self.output[1] {
+                level += volume;
+            }
+        } else if (self.control & 0x40) != 0 && (da_mode || (self.noise & 1) != 0) {
+            level += volume;
+        }
+        level
+    }
+
+    fn push_sample(&mut self, sample: i16) {
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push(sample);
+        }
+
+        let limit = (self.sample_rate * MAX_BUFFERED_SECONDS) as usize;
+        if self.samples.len() >= limit {
+            self.samples.drain(..limit / 2);
+        }
+        self.samples.push(sample);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOCK: u32 = 1_773_447;
+
+    fn run(sound: &mut TedSound, cycles: u32) -> Vec<i16> {
+        for _ in 0..cycles {
+            sound.tick();
+        }
+        sound.take_samples()
+    }
+
+    // Rising edges of the square wave
+    fn rising_edges(samples: &[i16]) -> usize {
+        samples.windows(2).filter(|pair| pair[0] == 0 && pair[1] > 0).count()
+    }
+
+    #[test]
+    fn test_sample_rate() {
+        let mut sound = TedSound::new(CLOCK, SAMPLE_RATE);
+        let samples = run(&mut sound, CLOCK / 10);
+        assert!(samples.len().abs_diff(SAMPLE_RATE as usize / 10) <= 1);
+        assert!(samples.iter().all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn test_square_wave_frequency() {
+        let mut sound = TedSound::new(CLOCK, SAMPLE_RATE);
+        // 1024 - 0x3A0 = 96 ticks per half period, 16 * 96 double cycles
+        // per period: about 1155 Hz
+        sound.write_register(0xFF0E, 0xA0);
+        sound.write_register(0xFF12, 0x03);
+        sound.write_register(0xFF11, 0x18);
+
+        let samples = run(&mut sound, CLOCK);
+        let expected = CLOCK as usize / (16 * 96);
+        assert!(rising_edges(&samples).abs_diff(expected) <= expected / 100, "{}", rising_edges(&samples));
+        assert_eq!(*samples.iter().max().unwrap(), (8 * VOLUME_STEP) as i16);
+    }
+
+    #[test]
+    fn test_volume_and_da_mode() {
+        let mut sound = TedSound::new(CLOCK, SAMPLE_RATE);
+        sound.write_register(0xFF0E, 0x00);
+        sound.write_register(0xFF0F, 0x00);
+
+        // Both voices in D/A mode at volume 3: constant level
+        sound.write_register(0xFF11, 0xB3);
+        let samples = run(&mut sound, CLOCK / 100);
+        assert!(samples.iter().all(|&sample| sample == (6 * VOLUME_STEP) as i16));
+
+        // Volume 0 is silent, after the sample spanning the register write
+        sound.write_register(0xFF11, 0xB0);
+        let samples = run(&mut sound, CLOCK / 100);
+        assert!(samples[1..].iter().all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn test_noise() {
+        let mut sound = TedSound::new(CLOCK, SAMPLE_RATE);
+        sound.write_register(0xFF0F, 0xF0);
+        sound.write_register(0xFF10, 0x03);
+        sound.write_register(0xFF11, 0x48);
+
+        let samples = run(&mut sound, CLOCK / 10);
+        let high = samples.iter().filter(|&&sample| sample > 0).count();
+        assert!(high > samples.len() / 4 && high < samples.len() * 3 / 4, "{}", high);
+    }
+
+    #[test]
+    fn test_recording_keeps_drained_samples() {
+        let mut sound = TedSound::new(CLOCK, SAMPLE_RATE);
+        sound.write_register(0xFF11, 0x98);
+        sound.start_recording();
+
+        let played = run(&mut sound, CLOCK / 100);
+        let recorded = sound.take_recorded();
+        assert_eq!(played, recorded);
+
+        let played = run(&mut sound, CLOCK / 100);
+        assert_eq!(sound.stop_recording(), played);
+        assert!(sound.take_recorded().is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+//! Symbol table for disassembly labels
+//! Copyright (C) 2025
+//! ROM labels from RomListing.java, Copyright (C) 2009 Florian Wolff (florian@donuz.de)
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+// Plus/4 KERNAL/BASIC entry points, as listed in RomListing.java
+const PLUS4_ROM_LABELS: &[(u16, &str)] = &[
+    // ZeroPage:
+    (0x00CA, "Cursor X position"),
+
+    // Extended ZeroPage:
+    (0x07E7, "Left text window border"),
+
+    // ROM:
+    (0x8000, "BASIC-Kaltstart"),
+    (0xCF11, "Stop-Taste ueberpruefen"),
+    (0xD84E, "EDITOR RESET"),
+    (0xD88B, "CLEAR SCREEN"),
+    (0xD89A, "HOME"),
+    (0xD8C1, "Code aus Tastaturpuffer holen"),
+    (0xD8EA, "Eingabe vom Bildschirm"),
+    (0xD965, "Zeichen vom Bildschirm holen"),
+    (0xD9BA, "Anführungszeichenmodus setzen/löschen"),
+    (0xD9C7, "Abschluss von PRINT"),
+    (0xDA21, "SCROLL UP"),
+    (0xDA3D, "Bildschirmzeile umkopieren"),
+    (0xDA5E, "Leerzeile einfuegen"),
+    (0xDB11, "Tastaturabfrage"),
+    (0xDB70, "Dekoder-Abfrage"),
+    (0xDC49, "PRINT"),
+    (0xDC8C, "RETURN CODE"),
+    (0xDC9B, "ESC-O (Flags löschen)"),
+    (0xDCFA, "CURSOR RIHGT"),
+    (0xDD00, "CURSOR DOWN"),
+    (0xDD0D, "CURSOR UP"),
+    (0xDD1C, "CURSOR LEFT"),
+    (0xDD27, "TEXT MODE"),
+    (0xDEF6, "ESC-V Scroll Up"),
+    (0xDF04, "ESC-W Scroll Down"),
+    (0xDF1D, "ESC-L Scrolling freigeben"),
+    (0xDF20, "ESC-M Scrolling sperren"),
+    (0xDF26, "ESC-C Auto-Insert aus"),
+    (0xDF2F, "Zeichen vom Bildschirm holen"),
+    (0xDF39, "GETBIT"),
+    (0xDF46, "PUTBIT"),
+    (0xDF4A, "CLRBIT"),
+    (0xDF66, "Erzeugt Bitposition"),
+    (0xDF95, "ESC-K Setzt Cursor ans Zeilenende"),
+    (0xDFD4, "CURSOR LEFT"),
+    (0xEB46, "RS-232-Arbeitsbereich initialisieren"),
+    (0xEDEA, "Teile von IOINIT"),
+    (0xF2A4, "NMI und Start"),
+    (0xF2CE, "RESTOR"),
+    (0xF2D3, "VECTOR"),
+    (0xF30B, "IOINIT"),
+    (0xF352, "RAMTAS"),
+    (0xF445, "MONITOR"),
+    (0xFBCB, "Stop-Taste pruefen"),
+    (0xFBD8, "Meldung ausgeben"),
+    (0xFC19, "IOBASE"),
+    (0xFC1E, "Modul-Reset"),
+    (0xFC59, "Module initialisieren"),
+    (0xFC7F, "Modul-Zugriff"),
+    (0xFC89, "Modul-Aufruf"),
+    (0xFCB3, "PULS"),
+    (0xFCC9, "Modul einschalten und ausführen"),
+    (0xFFF6, "SYSTEM START"),
+];
+
+// Documented KERNAL jump table entries, shared with the other 8-bit
+// Commodore machines (Plus/4 Programmer's Reference Guide); not part of
+// RomListing.java
+const KERNAL_JUMP_TABLE: &[(u16, &str)] = &[
+    (0xFF81, "CINT"),
+    (0xFF84, "IOINIT (Sprungtabelle)"),
+    (0xFF87, "RAMTAS (Sprungtabelle)"),
+    (0xFF8A, "RESTOR (Sprungtabelle)"),
+    (0xFFBA, "SETLFS"),
+    (0xFFBD, "SETNAM"),
+    (0xFFC0, "OPEN"),
+    (0xFFC3, "CLOSE"),
+    (0xFFC6, "CHKIN"),
+    (0xFFC9, "CKOUT"),
+    (0xFFCC, "CLRCH"),
+    (0xFFCF, "BASIN"),
+    (0xFFD2, "BSOUT"),
+    (0xFFD5, "LOAD"),
+    (0xFFD8, "SAVE"),
+    (0xFFDB, "SETTIM"),
+    (0xFFDE, "RDTIM"),
+    (0xFFE1, "STOP"),
+    (0xFFE4, "GETIN"),
+    (0xFFE7, "CLALL"),
+    (0xFFF0, "PLOT"),
+];
+
+/// Address-to-name mapping used to label disassembly and traces
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    labels: HashMap<u16, String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Symbol table with the built-in Plus/4 ROM entry points
+    pub fn plus4_rom() -> Self {
+        let mut table = Self::new();
+        for &(addr, name) in PLUS4_ROM_LABELS.iter().chain(KERNAL_JUMP_TABLE) {
+            table.insert(addr, name);
+        }
+        table
+    }
+
+    pub fn insert(&mut self, addr: u16, name: &str) {
+        self.labels.insert(addr, name.to_string());
+    }
+
+    pub fn get(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(|name| name.as_str())
+    }
+
+    /// Load symbols from a text file, adding to (and overriding) existing entries.
+    /// Returns the number of symbols read.
+    ///
+    /// One symbol per line, either `<address> <name>` (address as `$D88B`,
+    /// `0xD88B` or `D88B`, optionally followed by `=` or `:`) or the VICE
+    /// label format `al C:d88b .name`. Empty lines and lines starting with
+    /// `;` or `#` are ignored.
+    pub fn load_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        let text = fs::read_to_string(path)?;
+        self.load_from_str(&text)
+    }
+
+    pub fn load_from_str(&mut self, text: &str) -> Result<usize> {
+        let mut count = 0;
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            let (addr, name) = parse_line(line).ok_or_else(|| Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid symbol definition in line {}: {}", line_no + 1, line),
+            ))?;
+            self.insert(addr, name);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+fn parse_line(line: &str) -> Option<(u16, &str)> {
+    // VICE format: al C:d88b .name
+    if let Some(rest) = line.strip_prefix("al ") {
+        let (addr, name) = rest.trim().split_once(char::is_whitespace)?;
+        let addr = addr.strip_prefix("C:").unwrap_or(addr);
+        let name = name.trim().trim_start_matches('.');
+        return Some((parse_address(addr)?, name)).filter(|(_, name)| !name.is_empty());
+    }
+
+    let (addr, name) = line.split_once(|c: char| c.is_whitespace() || c == '=' || c == ':')?;
+    let name = name.trim_start_matches(|c: char| c.is_whitespace() || c == '=' || c == ':').trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some((parse_address(addr)?, name))
+}
+
+fn parse_address(text: &str) -> Option<u16> {
+    let digits = text.strip_prefix('$')
+        .or_else(|| text.strip_prefix("0x"))
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u16::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_labels() {
+        let table = SymbolTable::plus4_rom();
+        assert_eq!(table.get(0xD88B), Some("CLEAR SCREEN"));
+        assert_eq!(table.get(0xDB11), Some("Tastaturabfrage"));
+        assert_eq!(table.get(0x1234), None);
+    }
+
+    #[test]
+    fn test_load_symbol_formats() {
+        let mut table = SymbolTable::new();
+        let count = table.load_from_str(
+            "; comment\n$1000 start\n0x1010=loop\n2000: data table\nal C:d88b .clrscr\n\n",
+        ).unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(table.get(0x1000), Some("start"));
+        assert_eq!(table.get(0x1010), Some("loop"));
+        assert_eq!(table.get(0x2000), Some("data table"));
+        assert_eq!(table.get(0xD88B), Some("clrscr"));
+    }
+
+    #[test]
+    fn test_load_invalid_line() {
+        let mut table = SymbolTable::new();
+        assert!(table.load_from_str("$1000 start\nnonsense\n").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! TED timers
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+/// IRR bits (0xFF09) of timers A, B and C
+pub const TIMER_IRQ_BITS: [u8; 3] = [0x08, 0x10, 0x40];
+
+/// The three 16-bit TED down counters at 0xFF00-0xFF05. Writing the low
+/// byte stops a timer and writing the high byte starts it. Timer A reloads
+/// from its latch when it reaches zero; B and C free-run from $FFFF.
+pub struct TedTimers {
+    // Cycles until the counter reaches zero; 0x10000 reads back as 0
+    counter: [u32; 3],
+    // Timer A reload value
+    latch: u16,
+    running: [bool; 3],
+}
+
+impl Default for TedTimers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TedTimers {
+    pub fn new() -> Self {
+        Self {
+            counter: [0x10000; 3],
+            latch: 0,
+            running: [false; 3],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Current counter value for a CPU read of 0xFF00-0xFF05
+    pub fn read(&self, addr: u16) -> u8 {
+        let timer = ((addr & 0x0F) / 2) as usize;
+        let value = self.counter[timer] as u16;
+        if addr & 1 == 0 {
+            value as u8
+        } else {
+            (value >> 8) as u8
+        }
+    }
+
+    /// CPU write to 0xFF00-0xFF05
+    pub fn write(&mut self, addr: u16, value: u8) {
+        let timer = ((addr & 0x0F) / 2) as usize;
+        let high = addr & 1 != 0;
+
+        if timer == 0 {
+            // Timer A: the write goes to the latch, the high byte loads the counter
+            self.latch = if high {
+                (self.latch & 0x00FF) | ((value as u16) << 8)
+            } else {
+                (self.latch & 0xFF00) | value as u16
+            };
+            if high {
+                self.counter[0] = Self::period(self.latch);
+            }
+        } else {
+            let current = self.counter[timer] as u16;
+            let updated = if high {
+                (current & 0x00FF) | ((value as u16) << 8)
+            } else {
+                (current & 0xFF00) | value as u16
+            };
+            self.counter[timer] = Self::period(updated);
+        }
+
+        self.running[timer] = high;
+    }
+
+    /// Count one single-clock cycle. Returns the IRR bits of the timers
+    /// that reached zero.
+    pub fn tick(&mut self) -> u8 {
+        let mut irq = 0;
+        for (timer, &bit) in TIMER_IRQ_BITS.iter().enumerate() {
+            if !self.running[timer] {
+                continue;
+            }
+            self.counter[timer] -= 1;
+            if self.counter[timer] == 0 {
+                irq |= bit;
+                self.counter[timer] = if timer == 0 {
+                    Self::period(self.latch)
+                } else {
+                    0x10000
+                };
+            }
+        }
+        irq
+    }
+
+    // A start value of 0 counts a full 65536 cycles
+    fn period(value: u16) -> u32 {
+        if value == 0 { 0x10000 } else { value as u32 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_word(timers: &mut TedTimers, addr: u16, value: u16) {
+        timers.write(addr, value as u8);
+        timers.write(addr + 1, (value >> 8) as u8);
+    }
+
+    fn read_word(timers: &TedTimers, addr: u16) -> u16 {
+        timers.read(addr) as u16 | ((timers.read(addr + 1) as u16) << 8)
+    }
+
+    #[test]
+    fn test_timer_a_reloads_from_latch() {
+        let mut timers = TedTimers::new();
+        write_word(&mut timers, 0xFF00, 3);
+
+        assert_eq!(timers.tick(), 0);
+        assert_eq!(read_word(&timers, 0xFF00), 2);
+        assert_eq!(timers.tick(), 0);
+        assert_eq!(timers.tick(), 0x08);
+        assert_eq!(read_word(&timers, 0xFF00), 3);
+
+        // Period of 3 cycles between interrupts
+        let fired: Vec<u8> = (0..6).map(|_| timers.tick()).collect();
+        assert_eq!(fired, [0, 0, 0x08, 0, 0, 0x08]);
+    }
+
+    #[test]
+    fn test_timers_b_and_c_free_run() {
+        let mut timers = TedTimers::new();
+        write_word(&mut timers, 0xFF02, 2);
+        write_word(&mut timers, 0xFF04, 1);
+
+        assert_eq!(timers.tick(), 0x40);
+        assert_eq!(read_word(&timers, 0xFF04), 0);
+        assert_eq!(timers.tick(), 0x10);
+        assert_eq!(read_word(&timers, 0xFF02), 0);
+        assert_eq!(read_word(&timers, 0xFF04), 0xFFFF);
+        assert_eq!(timers.tick(), 0);
+        assert_eq!(read_word(&timers, 0xFF02), 0xFFFF);
+        assert_eq!(read_word(&timers, 0xFF04), 0xFFFE);
+    }
+
+    #[test]
+    fn test_low_byte_write_stops_timer() {
+        let mut timers = TedTimers::new();
+        write_word(&mut timers, 0xFF02, 0x1234);
+        timers.tick();
+        assert_eq!(read_word(&timers, 0xFF02), 0x1233);
+
+        timers.write(0xFF02, 0x80);
+        for _ in 0..10 {
+            assert_eq!(timers.tick(), 0);
+        }
+        assert_eq!(read_word(&timers, 0xFF02), 0x1280);
+
+        // Writing the high byte starts it again
+        timers.write(0xFF03, 0x00);
+        timers.tick();
+        assert_eq!(read_word(&timers, 0xFF02), 0x007F);
+    }
+
+    #[test]
+    fn test_timer_a_latch_survives_counting() {
+        let mut timers = TedTimers::new();
+        write_word(&mut timers, 0xFF00, 0x0100);
+        for _ in 0..0x80 {
+            timers.tick();
+        }
+        assert_eq!(read_word(&timers, 0xFF00), 0x0080);
+
+        // A new low byte stops the timer and only changes the latch
+        timers.write(0xFF00, 0x10);
+        assert_eq!(read_word(&timers, 0xFF00), 0x0080);
+        timers.write(0xFF01, 0x00);
+        assert_eq!(read_word(&timers, 0xFF00), 0x0010);
+    }
+}
@@ -0,0 +1,265 @@
+//! Instruction trace logger
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Error, Result, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// CPU state at the start of one traced instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub opcode: u8,
+    pub disassembly: String,
+    pub acc: u8,
+    pub xr: u8,
+    pub yr: u8,
+    pub sp: u8,
+    pub flags: u8,
+    pub cycles: u64,
+    pub raster_line: u32,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const FLAG_NAMES: &[u8; 8] = b"NV-BDIZC";
+        let flags: String = FLAG_NAMES.iter().enumerate()
+            .map(|(bit, &name)| if self.flags & (0x80 >> bit) != 0 { name as char } else { '.' })
+            .collect();
+        write!(f, "{:<48} A:{:02X} X:{:02X} Y:{:02X} SP:{:02X} {} CYC:{} LINE:{}",
+               self.disassembly, self.acc, self.xr, self.yr, self.sp, flags, self.cycles, self.raster_line)
+    }
+}
+
+/// Which memory the traced code must be running from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionFilter {
+    #[default]
+    All,
+    RomOnly,
+    RamOnly,
+}
+
+/// Selects which instructions get recorded
+#[derive(Debug, Clone, Default)]
+pub struct TraceFilter {
+    /// PC ranges to record; empty records every address
+    pub address_ranges: Vec<RangeInclusive<u16>>,
+    pub execution: ExecutionFilter,
+    /// Recording starts when PC reaches this address
+    pub start_trigger: Option<u16>,
+    /// Recording stops after the instruction at this address
+    pub stop_trigger: Option<u16>,
+}
+
+impl TraceFilter {
+    fn matches(&self, pc: u16, in_rom: bool) -> bool {
+        let range_ok = self.address_ranges.is_empty()
+            || self.address_ranges.iter().any(|range| range.contains(&pc));
+        let memory_ok = match self.execution {
+            ExecutionFilter::All => true,
+            ExecutionFilter::RomOnly => in_rom,
+            ExecutionFilter::RamOnly => !in_rom,
+        };
+        range_ok && memory_ok
+    }
+}
+
+enum TraceOutput {
+    RingBuffer { entries: VecDeque<TraceEntry>, capacity: usize },
+    File(BufWriter<File>),
+}
+
+/// Opt-in tracer attached to `Plus4::tracer`
+pub struct Tracer {
+    pub filter: TraceFilter,
+    output: TraceOutput,
+    // Between start and stop trigger
+    armed: bool,
+    stopped: bool,
+    // First failed write to the trace file, reported by flush()
+    write_error: Option<Error>,
+}
+
+impl Tracer {
+    /// Keep the last `capacity` traced instructions in memory
+    pub fn ring_buffer(capacity: usize) -> Self {
+        Self::with_output(TraceOutput::RingBuffer {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        })
+    }
+
+    /// Write every traced instruction to a text file
+    pub fn to_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::with_output(TraceOutput::File(BufWriter::new(file))))
+    }
+
+    fn with_output(output: TraceOutput) -> Self {
+        Self {
+            filter: TraceFilter::default(),
+            output,
+            armed: false,
+            stopped: false,
+            write_error: None,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: TraceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Decide whether the instruction at `pc` gets recorded, updating the
+    /// start/stop trigger state
+    pub fn should_trace(&mut self, pc: u16, in_rom: bool) -> bool {
+        if !self.armed {
+            // Without a start trigger recording runs from the beginning until
+            // the stop trigger; with one it restarts each time PC hits it
+            self.armed = match self.filter.start_trigger {
+                Some(start) => start == pc,
+                None => !self.stopped,
+            };
+            if !self.armed {
+                return false;
+            }
+        }
+
+        if self.filter.stop_trigger == Some(pc) {
+            self.armed = false;
+            self.stopped = true;
+        }
+
+        self.filter.matches(pc, in_rom)
+    }
+
+    pub fn record(&mut self, entry: TraceEntry) {
+        match &mut self.output {
+            TraceOutput::RingBuffer { entries, capacity } => {
+                if *capacity == 0 {
+                    return;
+                }
+                if entries.len() == *capacity {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+            TraceOutput::File(writer) => {
+                if self.write_error.is_none() {
+                    if let Err(e) = writeln!(writer, "{}", entry) {
+                        self.write_error = Some(e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recorded instructions, oldest first (always empty for file output)
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        let entries = match &self.output {
+            TraceOutput::RingBuffer { entries, .. } => Some(entries.iter()),
+            TraceOutput::File(_) => None,
+        };
+        entries.into_iter().flatten()
+    }
+
+    /// Flush the trace file. Fails with the first write error if recording
+    /// stopped early.
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(e) = self.write_error.take() {
+            return Err(e);
+        }
+        match &mut self.output {
+            TraceOutput::RingBuffer { .. } => Ok(()),
+            TraceOutput::File(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pc: u16) -> TraceEntry {
+        TraceEntry {
+            pc,
+            opcode: 0xEA,
+            disassembly: format!("{:04X}  EA         NOP", pc),
+            acc: 0,
+            xr: 0,
+            yr: 0,
+            sp: 0xFF,
+            flags: 0x24,
+            cycles: 0,
+            raster_line: 0,
+        }
+    }
+
+    fn run(tracer: &mut Tracer, pcs: &[(u16, bool)]) {
+        for &(pc, in_rom) in pcs {
+            if tracer.should_trace(pc, in_rom) {
+                tracer.record(entry(pc));
+            }
+        }
+    }
+
+    fn traced(tracer: &Tracer) -> Vec<u16> {
+        tracer.entries().map(|e| e.pc).collect()
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_latest() {
+        let mut tracer = Tracer::ring_buffer(2);
+        run(&mut tracer, &[(0x1000, false), (0x1001, false), (0x1002, false)]);
+        assert_eq!(traced(&tracer), vec![0x1001, 0x1002]);
+    }
+
+    #[test]
+    fn test_address_and_memory_filters() {
+        let mut tracer = Tracer::ring_buffer(16).with_filter(TraceFilter {
+            address_ranges: vec![0x1000..=0x10FF, 0xD800..=0xDFFF],
+            execution: ExecutionFilter::RamOnly,
+            ..Default::default()
+        });
+        run(&mut tracer, &[(0x0FFF, false), (0x1000, false), (0xD88B, true), (0xD88B, false), (0x2000, false)]);
+        assert_eq!(traced(&tracer), vec![0x1000, 0xD88B]);
+    }
+
+    #[test]
+    fn test_start_stop_triggers() {
+        let mut tracer = Tracer::ring_buffer(16).with_filter(TraceFilter {
+            start_trigger: Some(0x2000),
+            stop_trigger: Some(0x2002),
+            ..Default::default()
+        });
+        run(&mut tracer, &[(0x1000, false), (0x2000, false), (0x2001, false), (0x2002, false), (0x2003, false)]);
+        assert_eq!(traced(&tracer), vec![0x2000, 0x2001, 0x2002]);
+    }
+
+    #[test]
+    fn test_stop_trigger_without_start() {
+        let mut tracer = Tracer::ring_buffer(16).with_filter(TraceFilter {
+            stop_trigger: Some(0x1001),
+            ..Default::default()
+        });
+        run(&mut tracer, &[(0x1000, false), (0x1001, false), (0x1002, false)]);
+        assert_eq!(traced(&tracer), vec![0x1000, 0x1001]);
+    }
+
+    #[test]
+    fn test_entry_format() {
+        let mut e = entry(0x1000);
+        e.flags = 0x83;
+        let text = e.to_string();
+        assert!(text.ends_with("A:00 X:00 Y:00 SP:FF N.....ZC CYC:0 LINE:0"), "{}", text);
+    }
+}
@@ -0,0 +1,118 @@
+//! WAV encoding of emulator audio
+//! Copyright (C) 2025
+//!
+//! This program is free software; you can redistribute it and/or
+//! modify it under the terms of the GNU General Public License
+//! as published by the Free Software Foundation; either version 2
+//! of the License, or (at your option) any later version.
+
+use std::fs::File;
+use std::io::{BufWriter, Result, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// RIFF/WAVE header for mono 16-bit PCM with `data_len` bytes of samples
+pub fn wav_header(sample_rate: u32, data_len: u32) -> [u8; 44] {
+    let mut header = [0u8; 44];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&(36 + data_len).to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes()); // PCM
+    header[22..24].copy_from_slice(&1u16.to_le_bytes()); // mono
+    header[24..28].copy_from_slice(&sample_rate.to_le_bytes());
+    header[28..32].copy_from_slice(&(sample_rate * 2).to_le_bytes()); // bytes per second
+    header[32..34].copy_from_slice(&2u16.to_le_bytes()); // bytes per frame
+    header[34..36].copy_from_slice(&16u16.to_le_bytes()); // bits per sample
+
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// Complete in-memory WAV file of mono 16-bit samples
+pub fn encode_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(44 + samples.len() * 2);
+    bytes.extend_from_slice(&wav_header(sample_rate, (samples.len() * 2) as u32));
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+/// Streams mono 16-bit samples into a WAV file. The header sizes are
+/// updated after every write, so the file stays playable if the emulator
+/// is closed without finishing the recording.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    data_len: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> Result<Self> {
+        writer.write_all(&wav_header(sample_rate, 0))?;
+        Ok(Self { writer, sample_rate, data_len: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        for sample in samples {
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += (samples.len() * 2) as u32;
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&wav_header(self.sample_rate, self.data_len))?;
+        self.writer.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    /// Seconds of audio written so far
+    pub fn duration(&self) -> f32 {
+        self.data_len as f32 / 2.0 / self.sample_rate as f32
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_encode_wav() {
+        let bytes = encode_wav(&[0, 1, -1], 44100);
+        assert_eq!(bytes.len(), 50);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 42);
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 44100);
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 6);
+        assert_eq!(&bytes[44..], &[0x00, 0x00, 0x01, 0x00, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_wav_writer_matches_encoded() {
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), 22050).unwrap();
+        writer.write_samples(&[100, -100]).unwrap();
+        writer.write_samples(&[]).unwrap();
+        writer.write_samples(&[7]).unwrap();
+        assert_eq!(writer.duration(), 3.0 / 22050.0);
+
+        let bytes = writer.finish().unwrap().into_inner();
+        assert_eq!(bytes, encode_wav(&[100, -100, 7], 22050));
+    }
+}
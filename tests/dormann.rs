@@ -0,0 +1,102 @@
+//! Klaus Dormann's 6502 functional and decimal mode tests
+//!
+//! The test binaries are not part of the repository. Assemble them from
+//! https://github.com/Klaus2m5/6502_65C02_functional_tests (or copy the
+//! prebuilt images) into tests/roms/ as 6502_functional_test.bin and
+//! 6502_decimal_test.bin, then run them with `cargo test -- --ignored`.
+
+use std::path::Path;
+
+use plus4emu::Plus4;
+
+// Functional test: image loaded at $0000, entry at $0400, success trap at $3469
+const FUNCTIONAL_START: u16 = 0x0400;
+const FUNCTIONAL_SUCCESS: u16 = 0x3469;
+const FUNCTIONAL_TEST_CASE: u16 = 0x0200;
+
+// Decimal test: entry at $0200, error flag at $000B
+const DECIMAL_START: u16 = 0x0200;
+const DECIMAL_ERROR: u16 = 0x000B;
+
+const MAX_INSTRUCTIONS: u64 = 200_000_000;
+
+fn load_test_image(name: &str, base: u16) -> Plus4 {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms").join(name);
+    let data = std::fs::read(&path)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+
+    let mut emu = Plus4::new_bare_cpu();
+    // Full 64K images start at $0000, shorter ones at their assembly origin
+    let start = if data.len() == 0x10000 { 0x0000 } else { base };
+    emu.load_ram(start, &data);
+    emu
+}
+
+// Run from `start` until the CPU traps on a jump or branch to itself, jams,
+// or executes STP ($DB, as used by the decimal test). Returns the trap address.
+fn run_until_trap(emu: &mut Plus4, start: u16) -> u16 {
+    emu.cpu.pc = start;
+    for _ in 0..MAX_INSTRUCTIONS {
+        let pc = emu.cpu.pc;
+        if emu.peek(pc) == 0xDB {
+            return pc;
+        }
+        emu.execute_instruction();
+        if emu.cpu.pc == pc || emu.cpu.jammed {
+            return pc;
+        }
+    }
+    panic!("no trap after {} instructions, PC=${:04X}", MAX_INSTRUCTIONS, emu.cpu.pc);
+}
+
+#[test]
+fn harness_detects_trap() {
+    let mut emu = Plus4::new_bare_cpu();
+    // LDX #$05 / loop: DEX / BNE loop / STX $0200 / trap: JMP trap
+    emu.load_ram(
+        0x0400,
+        &[0xA2, 0x05, 0xCA, 0xD0, 0xFD, 0x8E, 0x00, 0x02, 0x4C, 0x08, 0x04],
+    );
+    emu.poke(0x0200, 0xFF);
+
+    assert_eq!(run_until_trap(&mut emu, 0x0400), 0x0408);
+    assert_eq!(emu.peek(0x0200), 0x00);
+}
+
+#[test]
+fn bare_cpu_has_flat_ram() {
+    let mut emu = Plus4::new_bare_cpu();
+    emu.poke(0xFF06, 0x12);
+    emu.poke(0xFFFE, 0x34);
+    assert_eq!(emu.peek(0xFF06), 0x12);
+    assert_eq!(emu.peek(0xFFFE), 0x34);
+}
+
+#[test]
+#[ignore = "needs tests/roms/6502_functional_test.bin"]
+fn functional_test() {
+    let mut emu = load_test_image("6502_functional_test.bin", 0x0000);
+
+    let trap = run_until_trap(&mut emu, FUNCTIONAL_START);
+    assert_eq!(
+        trap,
+        FUNCTIONAL_SUCCESS,
+        "functional test trapped at ${:04X} in test case ${:02X}",
+        trap,
+        emu.peek(FUNCTIONAL_TEST_CASE)
+    );
+}
+
+#[test]
+#[ignore = "needs tests/roms/6502_decimal_test.bin"]
+fn decimal_test() {
+    let mut emu = load_test_image("6502_decimal_test.bin", DECIMAL_START);
+
+    let trap = run_until_trap(&mut emu, DECIMAL_START);
+    assert_eq!(
+        emu.peek(DECIMAL_ERROR),
+        0,
+        "decimal test failed, trapped at ${:04X}",
+        trap
+    );
+}
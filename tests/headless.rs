@@ -0,0 +1,186 @@
+//! Headless use of the emulator core, without the macroquad frontend
+
+use plus4emu::{Cartridge, CartridgeSlot, MachineModel, Plus4, PrgFile, RamExpansion, RomPaths, RomSet};
+
+// Screen codes of the first text lines, as ASCII
+fn screen_text(emu: &Plus4, rows: u16) -> String {
+    let mut text = String::new();
+    for addr in 0x0C00..0x0C00 + rows * 40 {
+        let code = emu.peek(addr);
+        text.push(match code {
+            0x00..=0x1F => (code + 0x40) as char,
+            0x20..=0x3F => code as char,
+            _ => '.',
+        });
+    }
+    text
+}
+
+fn with_system_rom() -> Plus4 {
+    let mut emu = Plus4::new();
+    emu.load_rom_set(&RomSet::builtin());
+    emu
+}
+
+fn booted() -> Plus4 {
+    let mut emu = with_system_rom();
+    emu.hard_reset();
+    emu.run_cycles(3_000_000);
+    emu
+}
+
+#[test]
+fn boots_to_basic_prompt() {
+    let emu = booted();
+    let text = screen_text(&emu, 2);
+    assert!(text.contains("COMMODORE BASIC V3.5"), "{}", text);
+    assert!(emu.cycles() >= 3_000_000);
+}
+
+#[test]
+fn boots_without_function_rom() {
+    let paths = RomPaths { no_function_rom: true, ..Default::default() };
+    let mut emu = Plus4::new();
+    emu.load_rom_set(&RomSet::load(&paths).unwrap());
+    emu.hard_reset();
+    emu.run_cycles(3_000_000);
+    assert!(screen_text(&emu, 2).contains("COMMODORE BASIC V3.5"));
+}
+
+#[test]
+fn c16_boots_with_16k() {
+    let mut emu = Plus4::with_model(MachineModel::C16);
+    emu.load_rom_set(&RomSet::builtin());
+    emu.hard_reset();
+    emu.run_cycles(3_000_000);
+    let text = screen_text(&emu, 2);
+    assert!(text.contains("12277 BYTES FREE"), "{}", text);
+}
+
+#[test]
+fn boots_with_ram_expansion() {
+    for expansion in [RamExpansion::Hannes256K, RamExpansion::Hannes1M] {
+        let mut emu = with_system_rom();
+        emu.set_ram_expansion(expansion);
+        emu.hard_reset();
+        emu.run_cycles(3_000_000);
+        let text = screen_text(&emu, 2);
+        assert!(text.contains("COMMODORE BASIC V3.5"), "{:?}: {}", expansion, text);
+        // The KERNAL sizes the RAM under the ROM through $FF3F, in bank 0
+        assert!(text.contains("60671 BYTES FREE"), "{:?}: {}", expansion, text);
+    }
+}
+
+#[test]
+fn keeps_running_while_writing_ram_strobe() {
+    let mut emu = with_system_rom();
+    emu.set_ram_expansion(RamExpansion::Hannes256K);
+    emu.hard_reset();
+    emu.run_cycles(3_000_000);
+
+    // SEI, then STA $FF3F / STA $FF3F / INC $3000 / JMP $2001 forever
+    emu.load_prg(&PrgFile::from_data(0x2000, vec![
+        0x78, 0x8D, 0x3F, 0xFF, 0x8D, 0x3F, 0xFF, 0xEE, 0x00, 0x30, 0x4C, 0x01, 0x20,
+    ]));
+    emu.poke(0x3000, 0x00);
+    emu.cpu.pc = 0x2000;
+    emu.run_cycles(2_000);
+
+    assert!(emu.peek(0x3000) > 0x10, "{}", emu.peek(0x3000));
+    assert!((0x2001..0x200D).contains(&emu.cpu.pc), "{:04X}", emu.cpu.pc);
+    // The strobe pages the RAM in without touching the bank register
+    assert_eq!(emu.peek(0xFD16), 0x00);
+}
+
+#[test]
+fn loads_prg_into_memory() {
+    let mut emu = booted();
+    let prg = PrgFile::from_data(0x2000, vec![0xA9, 0x01, 0x60]);
+    emu.load_prg(&prg);
+    let lines = emu.disassemble(0x2000, 2);
+    assert_eq!(lines[0].mnemonic.as_str(), "LDA");
+    assert_eq!(lines[0].operand, "#$01");
+    assert_eq!(lines[1].address, 0x2002);
+}
+
+#[test]
+fn produces_audio_in_step_with_frames() {
+    let mut emu = booted();
+    // Start at the top of a frame
+    emu.run_frame();
+    emu.take_audio_samples();
+
+    // Voice 1 square wave at full volume
+    emu.poke(0xFF0E, 0x00);
+    emu.poke(0xFF12, emu.peek(0xFF12) | 0x03);
+    emu.poke(0xFF11, 0x18);
+    for _ in 0..10 {
+        emu.run_frame();
+    }
+
+    // 312 lines of 114 double-clock cycles per frame
+    let expected = 10 * 312 * 114 * emu.audio_sample_rate() as u64 / 1_773_447;
+    let samples = emu.take_audio_samples();
+    assert!((samples.len() as u64).abs_diff(expected) <= 2, "{} samples", samples.len());
+    assert!(samples.iter().any(|&sample| sample > 0));
+    assert!(samples.contains(&0));
+}
+
+#[test]
+fn records_audio_to_wav() {
+    let mut emu = booted();
+    let path = std::env::temp_dir().join(format!("plus4emu-record-{}.wav", std::process::id()));
+
+    emu.run_frame();
+    emu.start_recording(&path).unwrap();
+    assert!(emu.is_recording());
+    emu.poke(0xFF11, 0x98);
+    for _ in 0..5 {
+        emu.run_frame();
+        // Draining the playback buffer does not affect the recording
+        emu.take_audio_samples();
+    }
+    let duration = emu.stop_recording().unwrap();
+    assert!(!emu.is_recording());
+
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let data_len = u32::from_le_bytes(bytes[40..44].try_into().unwrap()) as usize;
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(bytes.len(), 44 + data_len);
+    assert!((duration - 0.1).abs() < 0.01, "{}", duration);
+    assert!(bytes[44..].iter().any(|&byte| byte != 0));
+}
+
+// Cartridge that the KERNAL starts at reset: JMP $800A, module number 1
+// and "CBM" at $8006, then SEI / LDA #$42 / STA $3000 / JMP *
+fn autostart_cartridge() -> Cartridge {
+    let mut rom = vec![0xFF; 0x4000];
+    rom[..19].copy_from_slice(&[
+        0x4C, 0x0A, 0x80, 0x00, 0x00, 0x00, 0x01, b'C', b'B', b'M',
+        0x78, 0xA9, 0x42, 0x8D, 0x00, 0x30, 0x4C, 0x10, 0x80,
+    ]);
+    Cartridge::from_raw(&rom).unwrap()
+}
+
+#[test]
+fn kernal_autostarts_cartridge() {
+    for slot in [CartridgeSlot::C1, CartridgeSlot::C2] {
+        let mut emu = with_system_rom();
+        emu.insert_cartridge(slot, &autostart_cartridge());
+        emu.hard_reset();
+        emu.run_cycles(1_000_000);
+        assert_eq!(emu.peek(0x3000), 0x42, "{:?}", slot);
+        assert_eq!(emu.cpu.pc, 0x8010, "{:?}", slot);
+    }
+}
+
+#[test]
+fn boots_to_basic_after_removing_cartridge() {
+    let mut emu = with_system_rom();
+    emu.insert_cartridge(CartridgeSlot::C1, &autostart_cartridge());
+    emu.remove_cartridge(CartridgeSlot::C1);
+    emu.hard_reset();
+    emu.run_cycles(3_000_000);
+    assert!(screen_text(&emu, 2).contains("COMMODORE BASIC V3.5"));
+}
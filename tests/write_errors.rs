@@ -0,0 +1,34 @@
+//! Write errors from the trace log and the WAV recording reach the caller
+#![cfg(target_os = "linux")]
+
+use plus4emu::trace::Tracer;
+use plus4emu::{Plus4, RomSet};
+
+// A file that accepts the open but fails every write
+const FULL_DEVICE: &str = "/dev/full";
+
+fn booted() -> Plus4 {
+    let mut emu = Plus4::new();
+    emu.load_rom_set(&RomSet::builtin());
+    emu.hard_reset();
+    emu.run_cycles(3_000_000);
+    emu
+}
+
+#[test]
+fn trace_write_error_is_returned_by_flush() {
+    let mut emu = booted();
+    emu.tracer = Some(Tracer::to_file(FULL_DEVICE).unwrap());
+    emu.run_frame();
+    assert!(emu.tracer.as_mut().unwrap().flush().is_err());
+}
+
+#[test]
+fn recording_write_error_is_returned_on_stop() {
+    let mut emu = booted();
+    emu.start_recording(FULL_DEVICE).unwrap();
+    emu.run_frame();
+    assert!(emu.is_recording());
+    assert!(emu.stop_recording().is_err());
+    assert!(!emu.is_recording());
+}